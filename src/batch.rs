@@ -0,0 +1,442 @@
+//! Reading of many payments at once, from CSV with one payment per record or from JSON Lines with one Pay
+//! per line, and naming of the files created for them.
+
+use serde::Serialize;
+
+use crate::error::{BysqrError, Rule};
+use crate::models::{optional_date, try_deserialize_pay, Amount, Pay, Payment};
+use crate::validator::ReportEntry;
+
+/// Fields of the Pay which can be read from a column of CSV, named the same as elements of the XSD.
+/// Each record has a single payment with a single bank account.
+pub const CSV_FIELDS: [&str; 14] = [
+    "InvoiceID",
+    "Amount",
+    "CurrencyCode",
+    "PaymentDueDate",
+    "VariableSymbol",
+    "ConstantSymbol",
+    "SpecificSymbol",
+    "OriginatorsReferenceInformation",
+    "PaymentNote",
+    "IBAN",
+    "BIC",
+    "BeneficiaryName",
+    "BeneficiaryAddressLine1",
+    "BeneficiaryAddressLine2",
+];
+
+/// Pattern the amount read from CSV has to match.
+const AMOUNT_PATTERN: &str = r"^\d+(\.\d{1,2})?$";
+
+/// Pay read from a single record of CSV or a line of JSON Lines.
+#[derive(Debug, Clone)]
+pub struct BatchRow {
+    /// Line of the source where the record starts, counted from 1.
+    pub line: usize,
+    /// Columns of the CSV record by their header, empty for JSON Lines.
+    pub columns: Vec<(String, String)>,
+    pub pay: Result<Pay, BysqrError>,
+}
+
+impl BatchRow {
+    /// Value for the placeholder of the file name template. Fields of the first payment are looked up first,
+    /// then columns of the CSV record. The "row" placeholder is the line of the source.
+    pub fn value(&self, name: &str) -> Option<String> {
+        if name == "row" {
+            return Some(self.line.to_string());
+        }
+
+        let field = self.pay.as_ref().ok().and_then(|pay| pay_value(pay, name));
+
+        field.or_else(|| {
+            self.columns.iter().find(|(column, _)| column == name).map(|(_, value)| value.clone())
+        })
+    }
+}
+
+fn pay_value(pay: &Pay, name: &str) -> Option<String> {
+    let payment = pay.payments.payment.first()?;
+    let bank_account = payment.bank_accounts.bank_account.first();
+
+    match name {
+        "InvoiceID" => pay.invoice_id.clone(),
+        "Amount" => payment.amount.map(|amount| amount.to_string()),
+        "CurrencyCode" => Some(payment.currency_code.to_string()),
+        "PaymentDueDate" => payment.payment_due_date.map(|date| date.format("%Y-%m-%d").to_string()),
+        "VariableSymbol" => payment.variable_symbol.as_ref().map(|symbol| symbol.to_string()),
+        "ConstantSymbol" => payment.constant_symbol.as_ref().map(|symbol| symbol.to_string()),
+        "SpecificSymbol" => payment.specific_symbol.as_ref().map(|symbol| symbol.to_string()),
+        "OriginatorsReferenceInformation" => payment.originators_reference_information.clone(),
+        "PaymentNote" => payment.payment_note.clone(),
+        "IBAN" => bank_account.map(|account| account.iban.to_string()),
+        "BIC" => bank_account.and_then(|account| account.bic.as_ref()).map(|bic| bic.to_string()),
+        "BeneficiaryName" => payment.beneficiary_name.clone(),
+        "BeneficiaryAddressLine1" => payment.beneficiary_address_line_1.clone(),
+        "BeneficiaryAddressLine2" => payment.beneficiary_address_line_2.clone(),
+        _ => None,
+    }
+}
+
+/// Split CSV content into records with the line where each of them starts. Fields may be quoted,
+/// with doubled quotes inside of them, so they can contain the delimiter and line breaks.
+fn parse_csv(content: &str, delimiter: char) -> Result<Vec<(usize, Vec<String>)>, BysqrError> {
+    let mut records: Vec<(usize, Vec<String>)> = Vec::new();
+    let mut record: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut record_line = 1;
+
+    let mut chars = content.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            line += 1;
+        }
+
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            _ if quoted => field.push(c),
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+
+                // Empty lines are skipped
+                if record.len() > 1 || !record[0].is_empty() {
+                    records.push((record_line, std::mem::take(&mut record)));
+                } else {
+                    record.clear();
+                }
+
+                record_line = line;
+            }
+            _ if c == delimiter => record.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(BysqrError::Deserialize(format!("quoted field starting at line {} is not terminated", record_line)));
+    }
+
+    if !record.is_empty() || !field.is_empty() {
+        record.push(field);
+        records.push((record_line, record));
+    }
+
+    Ok(records)
+}
+
+/// Create the Pay from fields of the CSV record, each with name from CSV_FIELDS.
+fn pay_from_record(fields: &[(&str, &str)]) -> Result<Pay, BysqrError> {
+    let mut payment = Payment::builder();
+    let mut invoice_id: Option<&str> = None;
+    let mut iban: Option<&str> = None;
+    let mut bic: Option<&str> = None;
+
+    for &(field, value) in fields {
+        let value = value.trim();
+
+        if value.is_empty() {
+            continue;
+        }
+
+        payment = match field {
+            "InvoiceID" => {
+                invoice_id = Some(value);
+                payment
+            }
+            "Amount" => {
+                let amount = value
+                    .parse::<Amount>()
+                    .map_err(|_| BysqrError::invalid_field("Payments.Payment[0].Amount", value, Rule::Pattern(AMOUNT_PATTERN)))?;
+
+                payment.amount(amount)
+            }
+            "CurrencyCode" => payment.currency_code(value),
+            "PaymentDueDate" => {
+                let date = optional_date::parse(value)
+                    .map_err(|rule| BysqrError::invalid_field("Payments.Payment[0].PaymentDueDate", value, rule))?;
+
+                payment.due_date(date)
+            }
+            "VariableSymbol" => payment.variable_symbol(value),
+            "ConstantSymbol" => payment.constant_symbol(value),
+            "SpecificSymbol" => payment.specific_symbol(value),
+            "OriginatorsReferenceInformation" => payment.originators_reference_information(value),
+            "PaymentNote" => payment.payment_note(value),
+            "IBAN" => {
+                iban = Some(value);
+                payment
+            }
+            "BIC" => {
+                bic = Some(value);
+                payment
+            }
+            "BeneficiaryName" => payment.beneficiary_name(value),
+            "BeneficiaryAddressLine1" => payment.beneficiary_address_line_1(value),
+            "BeneficiaryAddressLine2" => payment.beneficiary_address_line_2(value),
+            _ => payment,
+        };
+    }
+
+    payment = match (iban, bic) {
+        (Some(iban), Some(bic)) => payment.bank_account(iban, bic),
+        (Some(iban), None) => payment.iban(iban),
+        (None, _) => payment,
+    };
+
+    let mut pay = Pay::builder().payment(payment);
+
+    if let Some(invoice_id) = invoice_id {
+        pay = pay.invoice_id(invoice_id);
+    }
+
+    // The row keeps the first error, as when the Pay is read from JSON Lines
+    pay.build().map_err(|mut errors| errors.remove(0))
+}
+
+/// Read the Pay from each record of CSV with the header. Columns named the same as CSV_FIELDS are read
+/// into their fields, `mapping` assigns fields to other columns as pairs of column and field name.
+/// Fails only when the CSV itself can not be read, errors of the records are kept in their rows.
+pub fn read_csv(content: &str, delimiter: char, mapping: &[(String, String)]) -> Result<Vec<BatchRow>, BysqrError> {
+    let mut records = parse_csv(content, delimiter)?.into_iter();
+
+    let header = match records.next() {
+        Some((_, header)) => header,
+        None => return Ok(Vec::new()),
+    };
+
+    for (column, field) in mapping {
+        if !CSV_FIELDS.contains(&field.as_str()) {
+            return Err(BysqrError::Deserialize(format!(
+                "unknown field {} mapped to column {}, expected one of {}",
+                field,
+                column,
+                CSV_FIELDS.join(", ")
+            )));
+        }
+
+        if !header.contains(column) {
+            return Err(BysqrError::Deserialize(format!("column {} is not in the header", column)));
+        }
+    }
+
+    // Field of each column, explicit mapping takes precedence over the name of the column
+    let fields: Vec<Option<&str>> = header
+        .iter()
+        .map(|column| match mapping.iter().find(|(mapped, _)| mapped == column) {
+            Some((_, field)) => Some(field.as_str()),
+            None => CSV_FIELDS.iter().find(|field| *field == column).copied(),
+        })
+        .collect();
+
+    let rows = records
+        .map(|(line, record)| {
+            let values: Vec<(&str, &str)> = fields
+                .iter()
+                .zip(&record)
+                .filter_map(|(field, value)| field.map(|field| (field, value.as_str())))
+                .collect();
+
+            let pay = if record.len() == header.len() {
+                pay_from_record(&values)
+            } else {
+                Err(BysqrError::Deserialize(format!("expected {} columns, found {}", header.len(), record.len())))
+            };
+
+            BatchRow { line, columns: header.iter().cloned().zip(record.iter().cloned()).collect(), pay }
+        })
+        .collect();
+
+    Ok(rows)
+}
+
+/// Read the Pay from each non-empty line of JSON Lines, in the same JSON structure as accepted by try_deserialize_pay.
+/// Invalid values are reported with path of the field.
+pub fn read_json_lines(content: &str) -> Vec<BatchRow> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| BatchRow { line: index + 1, columns: Vec::new(), pay: try_deserialize_pay(line) })
+        .collect()
+}
+
+/// Create the file name from the template, replacing placeholders such as {VariableSymbol} with values
+/// of the row. Characters not allowed in file names are replaced by underscores.
+pub fn file_name(template: &str, row: &BatchRow) -> Result<String, BysqrError> {
+    let mut name = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| BysqrError::Template(format!("placeholder in {} is not closed", template)))?;
+
+        let placeholder = &rest[start + 1..end];
+
+        let value = row
+            .value(placeholder)
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| BysqrError::Template(format!("no value for placeholder {{{}}}", placeholder)))?;
+
+        name.push_str(&rest[..start]);
+        name.extend(value.chars().map(|c| if c.is_control() || r#"/\:*?"<>|"#.contains(c) { '_' } else { c }));
+
+        rest = &rest[end + 1..];
+    }
+
+    name.push_str(rest);
+
+    Ok(name)
+}
+
+/// Error of a single row of the batch.
+#[derive(Debug, Clone)]
+pub struct RowError {
+    pub line: usize,
+    pub error: BysqrError,
+}
+
+#[derive(Serialize)]
+struct JsonRowError<'a> {
+    line: usize,
+    #[serde(flatten)]
+    entry: ReportEntry<'a>,
+}
+
+/// Serialize errors of the rows to JSON, each with "line" of the source, "path" of the field,
+/// "value" and "message" the same way as ValidationReport::to_json.
+pub fn errors_to_json(errors: &[RowError]) -> Result<String, BysqrError> {
+    let errors: Vec<JsonRowError> = errors
+        .iter()
+        .map(|row| JsonRowError { line: row.line, entry: ReportEntry::from_error(&row.error) })
+        .collect();
+
+    serde_json::to_string_pretty(&errors).map_err(|e| BysqrError::Serialize(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{file_name, parse_csv, pay_from_record, read_csv, BatchRow};
+    use crate::error::{BysqrError, Rule};
+
+    const IBAN: &str = "SK8811000000002945102347";
+
+    fn invalid_path(error: &BysqrError) -> &str {
+        match error {
+            BysqrError::InvalidField { path, .. } => path,
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn parses_quoted_fields() {
+        let content = "a;b;c\n\"1;2\";\"line\nbreak\";\"say \"\"hi\"\"\"\r\n\nx;;z";
+        let records = parse_csv(content, ';').unwrap();
+
+        assert_eq!(records, [
+            (1, vec![String::from("a"), String::from("b"), String::from("c")]),
+            (2, vec![String::from("1;2"), String::from("line\nbreak"), String::from("say \"hi\"")]),
+            (5, vec![String::from("x"), String::new(), String::from("z")]),
+        ]);
+    }
+
+    #[test]
+    fn rejects_unterminated_quote_with_line() {
+        match parse_csv("a,b\n1,2\n\"3,4\n", ',') {
+            Err(BysqrError::Deserialize(message)) => assert_eq!(message, "quoted field starting at line 3 is not terminated"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reads_rows_with_their_lines_and_errors() {
+        let content = format!(
+            "IBAN,Amount,PaymentNote\n{},10,\"first\nnote\"\n{},1.999,second\n{},5\n{},7,\"a, b\"\n",
+            IBAN, IBAN, IBAN, IBAN,
+        );
+        let rows = read_csv(&content, ',', &[]).unwrap();
+        let lines: Vec<usize> = rows.iter().map(|row| row.line).collect();
+
+        assert_eq!(lines, [2, 4, 5, 6]);
+
+        let pay = rows[0].pay.as_ref().unwrap();
+        assert_eq!(pay.payments.payment[0].payment_note.as_deref(), Some("first\nnote"));
+        assert_eq!(pay.payments.payment[0].bank_accounts.bank_account[0].iban.as_str(), IBAN);
+
+        assert_eq!(invalid_path(rows[1].pay.as_ref().unwrap_err()), "Payments.Payment[0].Amount");
+
+        match &rows[2].pay {
+            Err(BysqrError::Deserialize(message)) => assert_eq!(message, "expected 3 columns, found 2"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        assert_eq!(rows[3].pay.as_ref().unwrap().payments.payment[0].payment_note.as_deref(), Some("a, b"));
+    }
+
+    #[test]
+    fn maps_columns_to_fields() {
+        let content = format!("Ucet;Suma;VS;Poznamka\n{};12.5;123;ignored\n", IBAN);
+        let mapping = [
+            (String::from("Ucet"), String::from("IBAN")),
+            (String::from("Suma"), String::from("Amount")),
+            (String::from("VS"), String::from("VariableSymbol")),
+        ];
+
+        let rows = read_csv(&content, ';', &mapping).unwrap();
+        let payment = &rows[0].pay.as_ref().unwrap().payments.payment[0];
+
+        assert_eq!(payment.amount.unwrap().to_string(), "12.5");
+        assert_eq!(payment.variable_symbol.as_ref().unwrap().to_string(), "123");
+        assert_eq!(payment.payment_note, None);
+        assert_eq!(rows[0].value("Poznamka").as_deref(), Some("ignored"));
+
+        let unknown = [(String::from("Ucet"), String::from("Account"))];
+        assert!(matches!(read_csv(&content, ';', &unknown), Err(BysqrError::Deserialize(_))));
+
+        let missing = [(String::from("Cislo"), String::from("IBAN"))];
+        assert!(matches!(read_csv(&content, ';', &missing), Err(BysqrError::Deserialize(_))));
+    }
+
+    #[test]
+    fn creates_pay_from_record() {
+        let pay = pay_from_record(&[("IBAN", IBAN), ("BIC", "TATRSKBX"), ("Amount", " 10 "), ("InvoiceID", "2024/1"), ("BIC", "")])
+            .unwrap();
+
+        assert_eq!(pay.invoice_id.as_deref(), Some("2024/1"));
+        assert_eq!(pay.payments.payment[0].amount.unwrap().to_string(), "10");
+        assert_eq!(pay.payments.payment[0].bank_accounts.bank_account[0].bic.as_ref().unwrap().as_str(), "TATRSKBX");
+
+        let error = pay_from_record(&[("IBAN", IBAN), ("PaymentDueDate", "2024-02-30")]).unwrap_err();
+        assert!(matches!(&error, BysqrError::InvalidField { rule: Rule::Date, .. }));
+        assert_eq!(invalid_path(&error), "Payments.Payment[0].PaymentDueDate");
+
+        let error = pay_from_record(&[("IBAN", "SK00")]).unwrap_err();
+        assert_eq!(invalid_path(&error), "Payments.Payment[0].BankAccounts.BankAccount[0].IBAN");
+    }
+
+    #[test]
+    fn fills_template_placeholders() {
+        let content = format!("IBAN,BeneficiaryName,VariableSymbol,Customer\n{},A/B: Test,123,42\n", IBAN);
+        let row: BatchRow = read_csv(&content, ',', &[]).unwrap().remove(0);
+
+        assert_eq!(file_name("{row}-{VariableSymbol}.svg", &row).unwrap(), "2-123.svg");
+        assert_eq!(file_name("{BeneficiaryName}/{Customer}.png", &row).unwrap(), "A_B_ Test/42.png");
+        assert_eq!(file_name("{IBAN}", &row).unwrap(), IBAN);
+
+        assert!(matches!(file_name("{SpecificSymbol}.svg", &row), Err(BysqrError::Template(_))));
+        assert!(matches!(file_name("{row.svg", &row), Err(BysqrError::Template(_))));
+    }
+}
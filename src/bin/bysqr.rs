@@ -1,15 +1,22 @@
+use std::collections::HashMap;
+use std::fmt::Display;
 use std::fs;
+use std::io::{self, Read};
 use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
 use clap::{Parser, Subcommand};
-use bysqr::{encoder, qr};
-use bysqr::models::{try_deserialize_pay, Pay};
+use qrcode::Version as QrVersion;
+use bysqr::{batch, decoder, encoder, inspect, qr, scanner, validator};
+use bysqr::error::BysqrError;
+use bysqr::models::{try_deserialize_pay, try_deserialize_pay_strict, Pay};
 #[path = "../preview.rs"]
 #[cfg(feature = "preview")]
 mod preview;
 #[path = "../utils.rs"]
 mod utils;
 use utils::ensure_directory_for_file;
-use serde_json;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None, arg_required_else_help = true)]
@@ -41,12 +48,89 @@ enum Commands {
 
         #[arg(long = "overwrite", required = false)]
         overwrite: bool,
-    }
+
+        /// Reject XML source with unknown elements or attributes, or elements occurring more or less times than allowed.
+        #[arg(long = "strict", required = false)]
+        strict: bool,
+    },
+    /// Decode the payment from the content of the code, given as a string, a file, or - for standard input.
+    Decode {
+        #[arg(long = "src", required = false)]
+        src: Option<String>,
+
+        /// Output format of the payment, json or xml.
+        #[arg(long = "format", required = false)]
+        format: Option<String>,
+    },
+    /// Print the header, lengths, checksums and labelled fields of the content of the code.
+    Inspect {
+        /// The encoded string, a file containing it, or - for standard input.
+        src: String,
+    },
+    /// Check the payment against every rule of the encoder, without creating the code.
+    Validate {
+        #[arg(long = "src", required = false)]
+        src: Option<String>,
+
+        /// Output format of the report, text or json.
+        #[arg(long = "format", required = false)]
+        format: Option<String>,
+
+        /// Reject XML source with unknown elements or attributes, or elements occurring more or less times than allowed.
+        #[arg(long = "strict", required = false)]
+        strict: bool,
+    },
+    /// Create an image for each record of CSV or each line of JSON Lines.
+    Batch {
+        /// CSV file with the header, or JSON Lines file with .jsonl extension.
+        #[arg(long = "src", required = false)]
+        src: Option<PathBuf>,
+
+        /// Directory where the images are saved.
+        #[arg(long = "out", required = false, default_value = ".")]
+        out: PathBuf,
+
+        /// Name of the image with placeholders such as {VariableSymbol}, {row} or CSV columns. Its extension sets the format.
+        #[arg(long = "name", required = false, default_value = "{row}.png")]
+        name: String,
+
+        /// Read the field from the CSV column of other name, e.g. --map "Tenant account=IBAN".
+        #[arg(long = "map", required = false)]
+        map: Vec<String>,
+
+        #[arg(long = "delimiter", required = false, default_value = ",")]
+        delimiter: char,
+
+        #[arg(long = "size", required = false, default_value = "512")]
+        size: u32,
+
+        #[arg(long = "quality", required = false, default_value = "90")]
+        quality: u8,
+
+        #[arg(long = "overwrite", required = false)]
+        overwrite: bool,
+
+        #[arg(long = "threads", required = false, default_value = "1")]
+        threads: usize,
+
+        /// Save errors of the rows to the JSON file.
+        #[arg(long = "report", required = false)]
+        report: Option<PathBuf>,
+    },
+    /// Read the payment from a PNG or JPEG image of the code.
+    Scan {
+        #[arg(long = "src", required = false)]
+        src: Option<PathBuf>,
+
+        /// Output format of the payment, json or xml.
+        #[arg(long = "format", required = false)]
+        format: Option<String>,
+    },
 }
 
 #[derive(Debug)]
 enum OutputFormat {
-    SVG, PNG, JPEG
+    Svg, Png, Jpeg
 }
 
 #[derive(Debug)]
@@ -58,14 +142,14 @@ enum OutputMode {
 fn guess_output_mode(destination: &Option<PathBuf>, requested_format: &Option<String>) -> Result<OutputMode, String> {
     if let Some(dest) = destination {
         if let Some(file_ext) = dest.extension() {
-            let format = file_ext.to_str().expect("unable to parse extension");
+            let format = file_ext.to_string_lossy();
 
             if format == "png" {
-                Ok(OutputMode::Save(PathBuf::from(dest), OutputFormat::PNG))
+                Ok(OutputMode::Save(PathBuf::from(dest), OutputFormat::Png))
             } else if format == "jpg" || format == "jpeg" {
-                Ok(OutputMode::Save(PathBuf::from(dest), OutputFormat::JPEG))
+                Ok(OutputMode::Save(PathBuf::from(dest), OutputFormat::Jpeg))
             } else if format == "svg" {
-                Ok(OutputMode::Save(PathBuf::from(dest), OutputFormat::SVG))
+                Ok(OutputMode::Save(PathBuf::from(dest), OutputFormat::Svg))
             } else {
                 Err(format!("invalid output: extension {} is not supported", format))
             }
@@ -75,11 +159,11 @@ fn guess_output_mode(destination: &Option<PathBuf>, requested_format: &Option<St
     } else {
         if let Some(format) = requested_format {
             if format == "png" {
-                Ok(OutputMode::Print(OutputFormat::PNG))
+                Ok(OutputMode::Print(OutputFormat::Png))
             } else if format == "jpg" || format == "jpeg" {
-                Ok(OutputMode::Print(OutputFormat::JPEG))
+                Ok(OutputMode::Print(OutputFormat::Jpeg))
             } else if format == "svg" {
-                Ok(OutputMode::Print(OutputFormat::SVG))
+                Ok(OutputMode::Print(OutputFormat::Svg))
             } else {
                 Err(format!("invalid output: extension {} is not supported", format))
             }
@@ -89,89 +173,351 @@ fn guess_output_mode(destination: &Option<PathBuf>, requested_format: &Option<St
     }
 }
 
-fn deserialize_pay(source: &str) -> Pay {
-    if fs::exists(source).unwrap() {
-        let content = fs::read_to_string(&source).expect("unable to read source file");
+fn render_image(svg_code: Vec<u8>, format: &OutputFormat, size: u32, quality: u8) -> Result<Vec<u8>, BysqrError> {
+    match format {
+        OutputFormat::Svg => Ok(svg_code),
+        OutputFormat::Png => qr::render_png(&svg_code, size),
+        OutputFormat::Jpeg => qr::render_jpeg(&svg_code, size, quality),
+    }
+}
+
+#[derive(Debug)]
+enum DocumentFormat {
+    Json, Xml
+}
+
+fn guess_document_format(requested_format: &Option<String>) -> Result<DocumentFormat, String> {
+    match requested_format.as_deref() {
+        None | Some("json") => Ok(DocumentFormat::Json),
+        Some("xml") => Ok(DocumentFormat::Xml),
+        Some(format) => Err(format!("invalid output: format {} is not supported, use json or xml", format)),
+    }
+}
+
+fn print_pay(pay: &Pay, format: DocumentFormat) {
+    let content = match format {
+        DocumentFormat::Json => pay.to_json(),
+        DocumentFormat::Xml => pay.to_xml(),
+    };
+
+    println!("{}", content.unwrap_or_else(|e| exit_with_error(e)));
+}
+
+fn deserialize_pay(source: &str, strict: bool) -> Result<Pay, String> {
+    let deserialize = if strict { try_deserialize_pay_strict } else { try_deserialize_pay };
+
+    if fs::exists(source).unwrap_or(false) {
+        let content = fs::read_to_string(source).map_err(|e| format!("unable to read source file: {}", e))?;
+
+        deserialize(&content).map_err(|e| e.to_string())
+    } else {
+        deserialize(source).map_err(|e| e.to_string())
+    }
+}
+
+/// Read the source given as a string, path to a file, or - for standard input.
+fn read_source(source: &str) -> Result<String, String> {
+    if source == "-" {
+        let mut content = String::new();
+        io::stdin().read_to_string(&mut content).map_err(|e| format!("unable to read standard input: {}", e))?;
 
-        try_deserialize_pay(&content)
-    } else if source.trim_start().starts_with("<?xml") {
-        quick_xml::de::from_str(&source).expect("unable to parse source as XML")
-    } else if source.trim_start().starts_with("{") {
-        serde_json::from_str(&source).expect("unable to parse source as JSON")
+        Ok(content)
+    } else if fs::exists(source).unwrap_or(false) {
+        fs::read_to_string(source).map_err(|e| format!("unable to read source file: {}", e))
     } else {
-        panic!("source does not seems to be a valid file or XML or JSON content")
+        Ok(String::from(source))
+    }
+}
+
+fn print_inspection(inspection: &inspect::Inspection) {
+    let [square_type, version, document_type, reserved] = inspection.nibbles;
+    let mismatch = |matches: bool| if matches { "" } else { " (mismatch)" };
+
+    println!("Header nibbles:      {} {} {} {}", square_type, version, document_type, reserved);
+    match &inspection.header {
+        Some(header) => println!("Specification:       {}", header.version),
+        None => println!("Specification:       unknown version {}", version),
+    }
+    println!("Declared length:     {}", inspection.declared_length);
+    println!("Actual length:       {}{}", inspection.actual_length, mismatch(inspection.length_matches()));
+    println!("Stored CRC32:        {:08x}", inspection.stored_checksum);
+    println!("Computed CRC32:      {:08x}{}", inspection.computed_checksum, mismatch(inspection.checksum_matches()));
+    println!("Compressed size:     {}", inspection.compressed_size);
+
+    match (inspection.qr_version, inspection.qr_error_correction) {
+        (Some(QrVersion::Normal(version)), Some(level)) => println!("QR code:             version {}, level {:?}", version, level),
+        (Some(QrVersion::Micro(version)), Some(level)) => println!("QR code:             micro version {}, level {:?}", version, level),
+        _ => println!("QR code:             the content does not fit into a QR code"),
+    }
+
+    println!();
+
+    let width = inspection.fields.iter().map(|field| field.path.len()).max().unwrap_or(0);
+
+    for field in &inspection.fields {
+        println!("{:<8} {:<width$} {:?}", field.order, field.path, field.value, width = width);
+    }
+}
+
+fn print_report(report: &validator::ValidationReport, format: &Option<String>) {
+    match format.as_deref() {
+        None | Some("text") => {
+            for error in &report.errors {
+                println!("{}", error);
+            }
+
+            for warning in &report.warnings {
+                println!("{}", warning);
+            }
+
+            println!("{} errors, {} warnings", report.errors.len(), report.warnings.len());
+        }
+        Some("json") => println!("{}", report.to_json().unwrap_or_else(|e| exit_with_error(e))),
+        Some(format) => exit_with_error(format!("invalid output: format {} is not supported, use text or json", format)),
     }
 }
 
+struct BatchOptions {
+    format: OutputFormat,
+    size: u32,
+    quality: u8,
+    threads: usize,
+}
+
+fn parse_mapping(map: &[String]) -> Vec<(String, String)> {
+    map.iter()
+        .map(|mapping| match mapping.rsplit_once('=') {
+            Some((column, field)) => (column.to_string(), field.to_string()),
+            None => exit_with_error(format!("invalid mapping {}, expected column=field", mapping)),
+        })
+        .collect()
+}
+
+fn encode_to_file(pay: &Pay, destination: &PathBuf, options: &BatchOptions) -> Result<(), BysqrError> {
+    let encoded = encoder::encode(pay)?;
+    let svg_code = qr::create_pay_svg(&encoded, qr::Theme::default())?;
+    let content = render_image(svg_code, &options.format, options.size, options.quality)?;
+
+    // Templates such as "{BeneficiaryName}/{row}.svg" put the file into a directory of its own
+    ensure_directory_for_file(destination)
+        .map_err(|e| BysqrError::Render(format!("unable to create directory for {}: {}", destination.display(), e)))?;
+
+    fs::write(destination, content)
+        .map_err(|e| BysqrError::Render(format!("unable to write {}: {}", destination.display(), e)))
+}
+
+/// Encode the jobs on the number of threads, returning errors of the rows which failed.
+fn encode_batch(jobs: &[(usize, Pay, PathBuf)], options: &BatchOptions) -> Vec<batch::RowError> {
+    let next = AtomicUsize::new(0);
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..options.threads.max(1))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut errors: Vec<batch::RowError> = Vec::new();
+
+                    while let Some((line, pay, destination)) = jobs.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        if let Err(error) = encode_to_file(pay, destination, options) {
+                            errors.push(batch::RowError { line: *line, error });
+                        }
+                    }
+
+                    errors
+                })
+            })
+            .collect();
+
+        workers.into_iter().flat_map(|worker| worker.join().unwrap_or_else(|_| exit_with_error("encoding thread panicked"))).collect()
+    })
+}
+
+#[cfg(feature = "pdf")]
+fn scan_pdf(source: &PathBuf) -> Result<Pay, String> {
+    let document = fs::read(source).map_err(|e| format!("unable to read document: {}", e))?;
+
+    scanner::scan_pdf_pay(&document).map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "pdf"))]
+fn scan_pdf(_source: &PathBuf) -> Result<Pay, String> {
+    Err(String::from("reading PDF documents is not available in this build, convert the page to an image first"))
+}
+
+fn exit_with_error(error: impl Display) -> ! {
+    eprintln!("{}", error);
+    process::exit(1)
+}
+
 fn main() {
     let cli = Cli::parse();
 
     match &cli.command {
         None => {}
-        Some(Commands::Encode { src, preview, format, save, size, quality, overwrite }) => {
+        Some(Commands::Encode { src, preview, format, save, size, quality, overwrite, strict }) => {
             if let Some(source) = src {
-                let pay: Pay = deserialize_pay(&source);
-                let encoded = encoder::encode(&pay);
+                let pay: Pay = deserialize_pay(source, *strict).unwrap_or_else(|e| exit_with_error(e));
+                let encoded = encoder::encode(&pay).unwrap_or_else(|e| exit_with_error(e));
 
-                let svg_code = qr::create_pay_svg(&encoded, qr::Theme::default());
+                let svg_code = qr::create_pay_svg(&encoded, qr::Theme::default()).unwrap_or_else(|e| exit_with_error(e));
 
                 if *preview {
                     #[cfg(feature = "preview")]
                     {
-                        preview::show_svg(svg_code.clone());
+                        preview::show_svg(svg_code.clone()).unwrap_or_else(|e| exit_with_error(e));
                     }
 
                     #[cfg(not(feature = "preview"))]
                     {
-                        panic!("Unable to run preview. This binary wasnt compiled with preview feature");
+                        exit_with_error("unable to run preview: this binary wasn't compiled with the preview feature");
                     }
                 } else {
-                    let output_mode = guess_output_mode(save, format).expect("unable to guess output file format");
+                    let output_mode = guess_output_mode(save, format).unwrap_or_else(|e| exit_with_error(e));
 
                     match output_mode {
                         OutputMode::Save(destination, format) => {
                             if destination.exists() && !*overwrite {
-                                panic!("output file already exists");
+                                exit_with_error(format!("invalid output: {} already exists, use --overwrite to replace it", destination.display()));
                             }
 
-                            let content = match format {
-                                OutputFormat::SVG => {
-                                    svg_code
-                                }
-                                OutputFormat::PNG => {
-                                    qr::render_png(&svg_code, *size)
-                                }
-                                OutputFormat::JPEG => {
-                                    qr::render_jpeg(&svg_code, *size, *quality)
-                                }
-                            };
+                            let content = render_image(svg_code, &format, *size, *quality).unwrap_or_else(|e| exit_with_error(e));
 
                             if destination.exists() {
-                                fs::remove_file(&destination).expect("unable to remove existing file");
+                                fs::remove_file(&destination)
+                                    .unwrap_or_else(|e| exit_with_error(format!("unable to remove {}: {}", destination.display(), e)));
                             }
 
-                            ensure_directory_for_file(&destination);
+                            ensure_directory_for_file(&destination)
+                                .unwrap_or_else(|e| exit_with_error(format!("unable to create directory for {}: {}", destination.display(), e)));
 
-                            fs::write(&destination, content).expect("unable to write output file");
+                            fs::write(&destination, content)
+                                .unwrap_or_else(|e| exit_with_error(format!("unable to write {}: {}", destination.display(), e)));
                         }
                         OutputMode::Print(format) => {
                             match format {
-                                OutputFormat::SVG => {
-                                    println!("{}", String::from_utf8(svg_code).expect("unable to decode XML content"));
+                                OutputFormat::Svg => {
+                                    println!("{}", String::from_utf8(svg_code).unwrap_or_else(|e| exit_with_error(format!("unable to decode XML content: {}", e))));
                                 }
-                                OutputFormat::PNG => {
-                                    println!("{}", qr::to_base64_png(&svg_code, *size));
+                                OutputFormat::Png => {
+                                    println!("{}", qr::to_base64_png(&svg_code, *size).unwrap_or_else(|e| exit_with_error(e)));
                                 }
-                                OutputFormat::JPEG => {
-                                    println!("{}", qr::to_base64_jpeg(&svg_code, *size, *quality));
+                                OutputFormat::Jpeg => {
+                                    println!("{}", qr::to_base64_jpeg(&svg_code, *size, *quality).unwrap_or_else(|e| exit_with_error(e)));
                                 }
                             }
                         }
                     }
                 }
             } else {
-                panic!("unable to read source");
+                exit_with_error("unable to read source: missing --src");
             }
         }
+        Some(Commands::Decode { src, format }) => {
+            let source = src.as_ref().unwrap_or_else(|| exit_with_error("unable to read source: missing --src"));
+            let format = guess_document_format(format).unwrap_or_else(|e| exit_with_error(e));
+
+            let pay = decoder::decode(read_source(source).unwrap_or_else(|e| exit_with_error(e)).trim()).unwrap_or_else(|e| exit_with_error(e));
+
+            print_pay(&pay, format);
+        }
+        Some(Commands::Inspect { src }) => {
+            let inspection = inspect::inspect(read_source(src).unwrap_or_else(|e| exit_with_error(e)).trim()).unwrap_or_else(|e| exit_with_error(e));
+
+            print_inspection(&inspection);
+        }
+        Some(Commands::Validate { src, format, strict }) => {
+            let source = src.as_ref().unwrap_or_else(|| exit_with_error("unable to read source: missing --src"));
+            let report = validator::validate_source(&read_source(source).unwrap_or_else(|e| exit_with_error(e)), *strict);
+
+            print_report(&report, format);
+
+            if !report.is_valid() {
+                process::exit(1);
+            }
+        }
+        Some(Commands::Batch { src, out, name, map, delimiter, size, quality, overwrite, threads, report }) => {
+            let source = src.as_ref().unwrap_or_else(|| exit_with_error("unable to read source: missing --src"));
+            let content = fs::read_to_string(source).unwrap_or_else(|e| exit_with_error(format!("unable to read source: {}", e)));
+
+            let rows = match source.extension().and_then(|extension| extension.to_str()) {
+                Some("csv") => batch::read_csv(&content, *delimiter, &parse_mapping(map)).unwrap_or_else(|e| exit_with_error(e)),
+                Some("jsonl") | Some("ndjson") => batch::read_json_lines(&content),
+                _ => exit_with_error("unable to read source: expected .csv or .jsonl file"),
+            };
+
+            let format = match guess_output_mode(&Some(PathBuf::from(name)), &None) {
+                Ok(OutputMode::Save(_, format)) => format,
+                Ok(OutputMode::Print(_)) => unreachable!("file name always gives save mode"),
+                Err(e) => exit_with_error(e),
+            };
+
+            fs::create_dir_all(out).unwrap_or_else(|e| exit_with_error(format!("unable to create {}: {}", out.display(), e)));
+
+            let total = rows.len();
+            let mut errors: Vec<batch::RowError> = Vec::new();
+            let mut jobs: Vec<(usize, Pay, PathBuf)> = Vec::new();
+            let mut names: HashMap<String, usize> = HashMap::new();
+
+            for row in rows {
+                let named = match &row.pay {
+                    Ok(pay) => batch::file_name(name, &row).map(|file_name| (pay.clone(), file_name)),
+                    Err(error) => Err(error.clone()),
+                };
+
+                let (pay, file_name) = match named {
+                    Ok(named) => named,
+                    Err(error) => {
+                        errors.push(batch::RowError { line: row.line, error });
+                        continue;
+                    }
+                };
+
+                let destination = out.join(&file_name);
+
+                if let Some(line) = names.get(&file_name) {
+                    let error = BysqrError::Template(format!("file name {} is already used by line {}", file_name, line));
+                    errors.push(batch::RowError { line: row.line, error });
+                } else if destination.exists() && !*overwrite {
+                    let error = BysqrError::Template(format!("{} already exists, use --overwrite to replace it", destination.display()));
+                    errors.push(batch::RowError { line: row.line, error });
+                } else {
+                    names.insert(file_name, row.line);
+                    jobs.push((row.line, pay, destination));
+                }
+            }
+
+            let options = BatchOptions { format, size: *size, quality: *quality, threads: *threads };
+            errors.extend(encode_batch(&jobs, &options));
+            errors.sort_by_key(|row| row.line);
+
+            for row in &errors {
+                eprintln!("line {}: {}", row.line, row.error);
+            }
+
+            println!("{} of {} rows encoded, {} failed", total - errors.len(), total, errors.len());
+
+            if let Some(report) = report {
+                let json = batch::errors_to_json(&errors).unwrap_or_else(|e| exit_with_error(e));
+                fs::write(report, json).unwrap_or_else(|e| exit_with_error(format!("unable to write report: {}", e)));
+            }
+
+            if !errors.is_empty() {
+                process::exit(1);
+            }
+        }
+        Some(Commands::Scan { src, format }) => {
+            let source = src.as_ref().unwrap_or_else(|| exit_with_error("unable to read source: missing --src image"));
+            let format = guess_document_format(format).unwrap_or_else(|e| exit_with_error(e));
+
+            let is_pdf = source.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"));
+
+            let pay = if is_pdf {
+                scan_pdf(source).unwrap_or_else(|e| exit_with_error(e))
+            } else {
+                let image = image::open(source).unwrap_or_else(|e| exit_with_error(format!("unable to open image: {}", e)));
+                scanner::scan_pay(&image).unwrap_or_else(|e| exit_with_error(e))
+            };
+
+            print_pay(&pay, format);
+        }
     }
 }
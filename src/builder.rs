@@ -0,0 +1,321 @@
+use chrono::NaiveDate;
+
+use crate::control::{sanitize_pay, sanitize_payment, ControlCharacters};
+use crate::encoder::{pay_to_seq, payment_to_seq};
+use crate::error::BysqrError;
+use crate::field::{is_within, parse_field};
+use crate::models::{
+    Amount, BankAccount, ConstantSymbol, CurrencyCode, DirectDebitExt, Iban, Pay, Payment, PaymentOption, PaymentOptions,
+    Payments, SpecificSymbol, StandingOrderExt, VariableSymbol,
+};
+
+/// Builder of the Pay, created by Pay::builder().
+#[derive(Debug, Clone, Default)]
+pub struct PayBuilder {
+    invoice_id: Option<String>,
+    payments: Vec<PaymentBuilder>,
+}
+
+impl Pay {
+    pub fn builder() -> PayBuilder {
+        PayBuilder::default()
+    }
+}
+
+impl PayBuilder {
+    pub fn invoice_id(mut self, invoice_id: impl Into<String>) -> Self {
+        self.invoice_id = Some(invoice_id.into());
+        self
+    }
+
+    /// Add the payment, either a PaymentBuilder or a Payment. The first added payment is the preferred one.
+    /// Values rejected by setters of the builder are reported by build() with path of the payment in the Pay.
+    pub fn payment(mut self, payment: impl Into<PaymentBuilder>) -> Self {
+        self.payments.push(payment.into());
+        self
+    }
+
+    /// Build the Pay, checking every field the same way as encode does. Returns errors of all invalid fields,
+    /// with the path in the Pay, e.g. "Payments.Payment[0].BankAccounts.BankAccount[0].IBAN".
+    pub fn build(self) -> Result<Pay, Vec<BysqrError>> {
+        let mut rejected: Vec<BysqrError> = Vec::new();
+        let mut payments: Vec<Payment> = Vec::new();
+
+        for (index, builder) in self.payments.into_iter().enumerate() {
+            let (payment, mut errors) = builder.into_parts(&format!("Payments.Payment[{}]", index));
+
+            payments.push(payment);
+            rejected.append(&mut errors);
+        }
+
+        let pay = Pay { invoice_id: self.invoice_id, payments: Payments { payment: payments } };
+
+        // Control characters are replaced by encode, so they are replaced before the check as well
+        let mut errors: Vec<BysqrError> = Vec::new();
+        let mut sanitized = pay.clone();
+        sanitize_pay(&mut sanitized, ControlCharacters::default(), &mut errors);
+        pay_to_seq(&sanitized, &mut errors);
+
+        into_result(pay, rejected, errors)
+    }
+}
+
+/// Join values rejected by setters with errors of the built document. Rejected values are left out or kept
+/// as placeholders, so the fields containing them are not reported again.
+fn into_result<T>(value: T, mut rejected: Vec<BysqrError>, mut errors: Vec<BysqrError>) -> Result<T, Vec<BysqrError>> {
+    let is_rejected = |path: &str| {
+        rejected.iter().any(|error| matches!(error, BysqrError::InvalidField { path: invalid, .. } if is_within(invalid, path)))
+    };
+
+    errors.retain(|error| !matches!(error, BysqrError::InvalidField { path, .. } if is_rejected(path)));
+    rejected.append(&mut errors);
+
+    if rejected.is_empty() {
+        Ok(value)
+    } else {
+        Err(rejected)
+    }
+}
+
+/// Builder of a single payment of the Pay.
+#[derive(Debug, Clone, Default)]
+pub struct PaymentBuilder {
+    payment: Payment,
+    /// Options set explicitly, otherwise they follow from the standing order and direct debit.
+    payment_options: Option<PaymentOptions>,
+    /// Values rejected by the setters with path relative to the payment, reported by build().
+    errors: Vec<BysqrError>,
+}
+
+impl From<Payment> for PaymentBuilder {
+    fn from(payment: Payment) -> Self {
+        PaymentBuilder { payment_options: Some(payment.payment_options), payment, errors: Vec::new() }
+    }
+}
+
+impl Payment {
+    pub fn builder() -> PaymentBuilder {
+        PaymentBuilder::default()
+    }
+}
+
+impl PaymentBuilder {
+    pub fn new() -> Self {
+        PaymentBuilder::default()
+    }
+
+    /// Add the bank account, keeping a rejected IBAN as placeholder, so the following accounts keep their index.
+    fn push_bank_account(&mut self, iban: &str, bic: Option<&str>) {
+        let path = format!("BankAccounts.BankAccount[{}]", self.payment.bank_accounts.bank_account.len());
+
+        let iban = parse_field(iban, &format!("{}.IBAN", path), &mut self.errors).unwrap_or_else(|| Iban::placeholder(iban));
+        let bic = bic.and_then(|bic| parse_field(bic, &format!("{}.BIC", path), &mut self.errors));
+
+        self.payment.bank_accounts.bank_account.push(BankAccount { iban, bic });
+    }
+
+    /// Set the payment options explicitly. When not set, the options are standingorder and directdebit
+    /// for the standing order and direct debit set up, or paymentorder when there is none of them.
+    pub fn payment_options(mut self, options: PaymentOptions) -> Self {
+        self.payment_options = Some(options);
+        self
+    }
+
+    pub fn amount(mut self, amount: Amount) -> Self {
+        self.payment.amount = Some(amount);
+        self
+    }
+
+    /// Currency in ISO 4217 format, e.g. "EUR", which is also the default.
+    pub fn currency_code(mut self, currency_code: &str) -> Self {
+        if let Some(currency_code) = parse_field::<CurrencyCode>(currency_code, "CurrencyCode", &mut self.errors) {
+            self.payment.currency_code = currency_code;
+        }
+        self
+    }
+
+    pub fn due_date(mut self, due_date: NaiveDate) -> Self {
+        self.payment.payment_due_date = Some(due_date);
+        self
+    }
+
+    pub fn variable_symbol(mut self, variable_symbol: &str) -> Self {
+        self.payment.variable_symbol = parse_field::<VariableSymbol>(variable_symbol, "VariableSymbol", &mut self.errors);
+        self
+    }
+
+    pub fn constant_symbol(mut self, constant_symbol: &str) -> Self {
+        self.payment.constant_symbol = parse_field::<ConstantSymbol>(constant_symbol, "ConstantSymbol", &mut self.errors);
+        self
+    }
+
+    pub fn specific_symbol(mut self, specific_symbol: &str) -> Self {
+        self.payment.specific_symbol = parse_field::<SpecificSymbol>(specific_symbol, "SpecificSymbol", &mut self.errors);
+        self
+    }
+
+    pub fn originators_reference_information(mut self, reference: impl Into<String>) -> Self {
+        self.payment.originators_reference_information = Some(reference.into());
+        self
+    }
+
+    pub fn payment_note(mut self, payment_note: impl Into<String>) -> Self {
+        self.payment.payment_note = Some(payment_note.into());
+        self
+    }
+
+    /// Add the bank account without BIC. The first added account is the preferred one.
+    pub fn iban(mut self, iban: &str) -> Self {
+        self.push_bank_account(iban, None);
+        self
+    }
+
+    /// Add the bank account with BIC. The first added account is the preferred one.
+    pub fn bank_account(mut self, iban: &str, bic: &str) -> Self {
+        self.push_bank_account(iban, Some(bic));
+        self
+    }
+
+    pub fn standing_order(mut self, standing_order: StandingOrderExt) -> Self {
+        self.payment.standing_order_ext = Some(standing_order);
+        self
+    }
+
+    pub fn direct_debit(mut self, direct_debit: DirectDebitExt) -> Self {
+        self.payment.direct_debit_ext = Some(direct_debit);
+        self
+    }
+
+    pub fn beneficiary_name(mut self, name: impl Into<String>) -> Self {
+        self.payment.beneficiary_name = Some(name.into());
+        self
+    }
+
+    pub fn beneficiary_address_line_1(mut self, line: impl Into<String>) -> Self {
+        self.payment.beneficiary_address_line_1 = Some(line.into());
+        self
+    }
+
+    pub fn beneficiary_address_line_2(mut self, line: impl Into<String>) -> Self {
+        self.payment.beneficiary_address_line_2 = Some(line.into());
+        self
+    }
+
+    /// Resolve the payment options and return the payment with values rejected by the setters,
+    /// their path rebased onto `path` of the payment.
+    fn into_parts(mut self, path: &str) -> (Payment, Vec<BysqrError>) {
+        self.payment.payment_options = self.payment_options.unwrap_or_else(|| {
+            match (&self.payment.standing_order_ext, &self.payment.direct_debit_ext) {
+                (Some(_), Some(_)) => PaymentOptions::from(PaymentOption::StandingOrder) | PaymentOption::DirectDebit.into(),
+                (Some(_), None) => PaymentOption::StandingOrder.into(),
+                (None, Some(_)) => PaymentOption::DirectDebit.into(),
+                (None, None) => PaymentOption::PaymentOrder.into(),
+            }
+        });
+
+        let errors = self
+            .errors
+            .into_iter()
+            .map(|error| match error {
+                BysqrError::InvalidField { path: field, value, rule } => {
+                    BysqrError::InvalidField { path: format!("{}.{}", path, field), value, rule }
+                }
+                error => error,
+            })
+            .collect();
+
+        (self.payment, errors)
+    }
+
+    /// Build the payment, checking every field the same way as encode does. Returns errors of all invalid fields,
+    /// with the path relative to the payment, e.g. "Payment.BankAccounts.BankAccount[0].IBAN".
+    pub fn build(self) -> Result<Payment, Vec<BysqrError>> {
+        let (payment, rejected) = self.into_parts("Payment");
+
+        let mut errors: Vec<BysqrError> = Vec::new();
+        let mut sanitized = payment.clone();
+        sanitize_payment(&mut sanitized, "Payment", ControlCharacters::default(), &mut errors);
+        payment_to_seq(&sanitized, "Payment", &mut errors);
+
+        into_result(payment, rejected, errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{BysqrError, Rule};
+    use crate::models::{try_deserialize_pay, Amount, Pay, Payment};
+
+    fn paths(errors: &[BysqrError]) -> Vec<&str> {
+        errors
+            .iter()
+            .map(|error| match error {
+                BysqrError::InvalidField { path, .. } => path.as_str(),
+                other => panic!("unexpected error: {}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn builds_example_payment() {
+        let pay = Pay::builder()
+            .payment(
+                Payment::builder()
+                    .amount("12.34".parse().unwrap())
+                    .bank_account("SK8811000000002945102347", "TATRSKBX")
+                    .currency_code("EUR")
+                    .beneficiary_name("Test")
+                    .payment_note("20202020")
+                    .variable_symbol("20202020")
+                    .beneficiary_address_line_1("Test"),
+            )
+            .build()
+            .unwrap();
+
+        assert_eq!(pay, try_deserialize_pay(include_str!("../example/payment.xml")).unwrap());
+    }
+
+    #[test]
+    fn returns_every_rejected_value_with_path() {
+        let errors = Pay::builder()
+            .payment(Payment::builder().amount("1".parse().unwrap()).iban("SK8811000000002945102347"))
+            .payment(
+                Payment::builder()
+                    .amount(Amount::from_minor_units(-100))
+                    .iban("SK0011000000002945102347")
+                    .variable_symbol("12A")
+                    .beneficiary_name("Test"),
+            )
+            .build()
+            .unwrap_err();
+
+        assert_eq!(paths(&errors), [
+            "Payments.Payment[1].BankAccounts.BankAccount[0].IBAN",
+            "Payments.Payment[1].VariableSymbol",
+            "Payments.Payment[1].Amount",
+        ]);
+        assert!(matches!(&errors[2], BysqrError::InvalidField { rule: Rule::Positive, .. }));
+    }
+
+    #[test]
+    fn keeps_index_of_accounts_after_rejected_one() {
+        let errors = Payment::builder()
+            .amount("1".parse().unwrap())
+            .iban("bad")
+            .bank_account("SK8811000000002945102347", "TATR")
+            .build()
+            .unwrap_err();
+
+        assert_eq!(paths(&errors), ["Payment.BankAccounts.BankAccount[0].IBAN", "Payment.BankAccounts.BankAccount[1].BIC"]);
+    }
+
+    #[test]
+    fn checks_fields_with_control_characters_replaced_as_encode_does() {
+        let pay = Pay::builder()
+            .payment(Payment::builder().iban("SK8811000000002945102347").payment_note("first\nline"))
+            .build()
+            .unwrap();
+
+        assert_eq!(pay.payments.payment[0].payment_note.as_deref(), Some("first\nline"));
+    }
+}
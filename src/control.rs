@@ -0,0 +1,236 @@
+use crate::error::{BysqrError, Rule};
+use crate::models::{
+    CustomerParty, DirectDebitExt, Invoice, InvoiceItems, InvoiceLine, Pay, Payment, PostalAddress,
+    SupplierParty,
+};
+
+/// Handling of control characters such as tab, CR or LF in values of fields. Fields of the sequence
+/// are separated by tabs, so a tab inside of a value would shift every following field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ControlCharacters {
+    /// Every control character is replaced by a space.
+    #[default]
+    Replace,
+    /// Fields containing a control character are reported as invalid.
+    Reject,
+}
+
+impl ControlCharacters {
+    /// Apply the handling to the value of the field on the path, errors of rejected fields are added to `errors`.
+    fn apply(&self, path: String, value: &mut String, errors: &mut Vec<BysqrError>) {
+        if !value.chars().any(char::is_control) {
+            return;
+        }
+
+        match self {
+            ControlCharacters::Replace => {
+                *value = value.chars().map(|c| if c.is_control() { ' ' } else { c }).collect();
+            }
+            // Escaped, so the reported value does not break the message over lines
+            ControlCharacters::Reject => {
+                errors.push(BysqrError::invalid_field(&path, &value.escape_debug().to_string(), Rule::ControlCharacter))
+            }
+        }
+    }
+}
+
+pub(crate) type Visitor<'a> = dyn FnMut(String, &mut String) + 'a;
+
+fn visit_optional(value: &mut Option<String>, path: String, visit: &mut Visitor) {
+    if let Some(value) = value {
+        visit(path, value);
+    }
+}
+
+fn direct_debit_ext_fields(ext: &mut DirectDebitExt, path: &str, visit: &mut Visitor) {
+    visit_optional(&mut ext.originators_reference_information, format!("{}.OriginatorsReferenceInformation", path), visit);
+    visit_optional(&mut ext.mandate_id, format!("{}.MandateID", path), visit);
+    visit_optional(&mut ext.creditor_id, format!("{}.CreditorID", path), visit);
+    visit_optional(&mut ext.contract_id, format!("{}.ContractID", path), visit);
+}
+
+fn payment_fields(payment: &mut Payment, path: &str, visit: &mut Visitor) {
+    visit_optional(&mut payment.originators_reference_information, format!("{}.OriginatorsReferenceInformation", path), visit);
+    visit_optional(&mut payment.payment_note, format!("{}.PaymentNote", path), visit);
+
+    if let Some(ext) = &mut payment.direct_debit_ext {
+        direct_debit_ext_fields(ext, &format!("{}.DirectDebitExt", path), visit);
+    }
+
+    visit_optional(&mut payment.beneficiary_name, format!("{}.BeneficiaryName", path), visit);
+    visit_optional(&mut payment.beneficiary_address_line_1, format!("{}.BeneficiaryAddressLine1", path), visit);
+    visit_optional(&mut payment.beneficiary_address_line_2, format!("{}.BeneficiaryAddressLine2", path), visit);
+}
+
+/// Call `visit` with path and value of every text field of the Pay. Fields with validated types,
+/// such as IBAN or symbols, cannot contain control characters and are not visited.
+pub(crate) fn pay_fields(pay: &mut Pay, visit: &mut Visitor) {
+    visit_optional(&mut pay.invoice_id, String::from("InvoiceID"), visit);
+
+    for (index, payment) in pay.payments.payment.iter_mut().enumerate() {
+        payment_fields(payment, &format!("Payments.Payment[{}]", index), visit);
+    }
+}
+
+fn postal_address_fields(address: &mut PostalAddress, path: &str, visit: &mut Visitor) {
+    visit(format!("{}.StreetName", path), &mut address.street_name);
+    visit_optional(&mut address.building_number, format!("{}.BuildingNumber", path), visit);
+    visit(format!("{}.CityName", path), &mut address.city_name);
+    visit(format!("{}.PostalZone", path), &mut address.postal_zone);
+    visit_optional(&mut address.state, format!("{}.State", path), visit);
+}
+
+fn supplier_party_fields(party: &mut SupplierParty, visit: &mut Visitor) {
+    visit(String::from("SupplierParty.PartyName"), &mut party.party_name);
+    visit_optional(&mut party.company_tax_id, String::from("SupplierParty.CompanyTaxID"), visit);
+    visit_optional(&mut party.company_vat_id, String::from("SupplierParty.CompanyVATID"), visit);
+    visit_optional(&mut party.company_register_id, String::from("SupplierParty.CompanyRegisterID"), visit);
+    postal_address_fields(&mut party.postal_address, "SupplierParty.PostalAddress", visit);
+
+    if let Some(contact) = &mut party.contact {
+        visit_optional(&mut contact.name, String::from("SupplierParty.Contact.Name"), visit);
+        visit_optional(&mut contact.telephone, String::from("SupplierParty.Contact.Telephone"), visit);
+        visit_optional(&mut contact.email, String::from("SupplierParty.Contact.EMail"), visit);
+    }
+}
+
+fn customer_party_fields(party: &mut CustomerParty, visit: &mut Visitor) {
+    visit(String::from("CustomerParty.PartyName"), &mut party.party_name);
+    visit_optional(&mut party.company_tax_id, String::from("CustomerParty.CompanyTaxID"), visit);
+    visit_optional(&mut party.company_vat_id, String::from("CustomerParty.CompanyVATID"), visit);
+    visit_optional(&mut party.company_register_id, String::from("CustomerParty.CompanyRegisterID"), visit);
+    visit_optional(&mut party.party_identification, String::from("CustomerParty.PartyIdentification"), visit);
+}
+
+/// Call `visit` with path and value of every text field of the Invoice.
+pub(crate) fn invoice_fields(invoice: &mut Invoice, visit: &mut Visitor) {
+    visit(String::from("InvoiceID"), &mut invoice.invoice_id);
+    visit_optional(&mut invoice.order_id, String::from("OrderID"), visit);
+    visit_optional(&mut invoice.delivery_note_id, String::from("DeliveryNoteID"), visit);
+    supplier_party_fields(&mut invoice.supplier_party, visit);
+    customer_party_fields(&mut invoice.customer_party, visit);
+    visit_optional(&mut invoice.invoice_description, String::from("InvoiceDescription"), visit);
+
+    if let Some(line) = &mut invoice.single_invoice_line {
+        visit_optional(&mut line.order_line_id, String::from("SingleInvoiceLine.OrderLineID"), visit);
+        visit_optional(&mut line.delivery_note_line_id, String::from("SingleInvoiceLine.DeliveryNoteLineID"), visit);
+        visit_optional(&mut line.item_name, String::from("SingleInvoiceLine.ItemName"), visit);
+        visit_optional(&mut line.item_ean_code, String::from("SingleInvoiceLine.ItemEANCode"), visit);
+    }
+}
+
+fn invoice_line_fields(line: &mut InvoiceLine, path: &str, visit: &mut Visitor) {
+    if let Some(reference) = &mut line.order_reference {
+        visit_optional(&mut reference.order_id, format!("{}.OrderReference.OrderID", path), visit);
+        visit_optional(&mut reference.order_line_id, format!("{}.OrderReference.OrderLineID", path), visit);
+    }
+
+    if let Some(reference) = &mut line.delivery_note_reference {
+        visit_optional(&mut reference.delivery_note_id, format!("{}.DeliveryNoteReference.DeliveryNoteID", path), visit);
+        visit_optional(&mut reference.delivery_note_line_id, format!("{}.DeliveryNoteReference.DeliveryNoteLineID", path), visit);
+    }
+
+    visit_optional(&mut line.item_name, format!("{}.ItemName", path), visit);
+    visit_optional(&mut line.item_ean_code, format!("{}.ItemEANCode", path), visit);
+}
+
+/// Call `visit` with path and value of every text field of the InvoiceItems.
+pub(crate) fn invoice_items_fields(items: &mut InvoiceItems, visit: &mut Visitor) {
+    visit(String::from("InvoiceID"), &mut items.invoice_id);
+    visit(String::from("FirstInvoiceLineID"), &mut items.first_invoice_line_id);
+
+    for (index, line) in items.invoice_lines.invoice_line.iter_mut().enumerate() {
+        invoice_line_fields(line, &format!("InvoiceLines.InvoiceLine[{}]", index), visit);
+    }
+}
+
+/// Replace or reject control characters in every text field of the Pay.
+pub(crate) fn sanitize_pay(pay: &mut Pay, handling: ControlCharacters, errors: &mut Vec<BysqrError>) {
+    pay_fields(pay, &mut |path, value| handling.apply(path, value, errors));
+}
+
+/// Replace or reject control characters in every text field of the payment on `path`.
+pub(crate) fn sanitize_payment(payment: &mut Payment, path: &str, handling: ControlCharacters, errors: &mut Vec<BysqrError>) {
+    payment_fields(payment, path, &mut |path, value| handling.apply(path, value, errors));
+}
+
+/// Replace or reject control characters in every text field of the Invoice.
+pub(crate) fn sanitize_invoice(invoice: &mut Invoice, handling: ControlCharacters, errors: &mut Vec<BysqrError>) {
+    invoice_fields(invoice, &mut |path, value| handling.apply(path, value, errors));
+}
+
+/// Replace or reject control characters in every text field of the InvoiceItems.
+pub(crate) fn sanitize_invoice_items(items: &mut InvoiceItems, handling: ControlCharacters, errors: &mut Vec<BysqrError>) {
+    invoice_items_fields(items, &mut |path, value| handling.apply(path, value, errors));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ControlCharacters;
+    use crate::decoder::{decode, decode_invoice, decode_invoice_items};
+    use crate::encoder::{
+        encode_invoice_items_with_options, encode_invoice_with_options, encode_with_options, EncodeOptions,
+    };
+    use crate::error::{BysqrError, Rule};
+    use crate::models::{try_deserialize_invoice, try_deserialize_invoice_items, try_deserialize_pay};
+    use crate::truncation::TruncationReport;
+
+    fn options(control_characters: ControlCharacters) -> EncodeOptions {
+        EncodeOptions { control_characters, ..EncodeOptions::default() }
+    }
+
+    fn assert_rejected(result: Result<(String, TruncationReport), BysqrError>, expected: &str) {
+        match result {
+            Err(BysqrError::InvalidField { path, value, rule: Rule::ControlCharacter }) => {
+                assert_eq!(path, expected);
+                assert_eq!(value, "a\\tb\\nc");
+            }
+            other => panic!("{} should be rejected, got {:?}", expected, other),
+        }
+    }
+
+    #[test]
+    fn replaces_control_characters_and_keeps_following_fields() {
+        let mut pay = try_deserialize_pay(include_str!("../example/payment.xml")).unwrap();
+        pay.payments.payment[0].payment_note = Some(String::from("a\tb\nc"));
+
+        let (encoded, _) = encode_with_options(&pay, &options(ControlCharacters::Replace)).unwrap();
+        pay.payments.payment[0].payment_note = Some(String::from("a b c"));
+        assert_eq!(decode(&encoded).unwrap(), pay);
+
+        let mut invoice = try_deserialize_invoice(include_str!("../example/invoice.xml")).unwrap();
+        invoice.supplier_party.postal_address.street_name = String::from("a\tb\nc");
+
+        let (encoded, _) = encode_invoice_with_options(&invoice, &options(ControlCharacters::Replace)).unwrap();
+        invoice.supplier_party.postal_address.street_name = String::from("a b c");
+        assert_eq!(decode_invoice(&encoded).unwrap(), invoice);
+
+        let mut items = try_deserialize_invoice_items(include_str!("../example/invoice_items.xml")).unwrap();
+        items.invoice_lines.invoice_line[0].item_name = Some(String::from("a\tb\nc"));
+
+        let (encoded, _) = encode_invoice_items_with_options(&items, &options(ControlCharacters::Replace)).unwrap();
+        items.invoice_lines.invoice_line[0].item_name = Some(String::from("a b c"));
+        assert_eq!(decode_invoice_items(&encoded).unwrap(), items);
+    }
+
+    #[test]
+    fn rejects_control_characters_with_path() {
+        let mut pay = try_deserialize_pay(include_str!("../example/payment.xml")).unwrap();
+        pay.payments.payment[0].payment_note = Some(String::from("a\tb\nc"));
+        assert_rejected(encode_with_options(&pay, &options(ControlCharacters::Reject)), "Payments.Payment[0].PaymentNote");
+
+        let mut invoice = try_deserialize_invoice(include_str!("../example/invoice.xml")).unwrap();
+        invoice.supplier_party.postal_address.street_name = String::from("a\tb\nc");
+        assert_rejected(
+            encode_invoice_with_options(&invoice, &options(ControlCharacters::Reject)),
+            "SupplierParty.PostalAddress.StreetName",
+        );
+
+        let mut items = try_deserialize_invoice_items(include_str!("../example/invoice_items.xml")).unwrap();
+        items.invoice_lines.invoice_line[1].item_name = Some(String::from("a\tb\nc"));
+        assert_rejected(
+            encode_invoice_items_with_options(&items, &options(ControlCharacters::Reject)),
+            "InvoiceLines.InvoiceLine[1].ItemName",
+        );
+    }
+}
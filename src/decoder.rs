@@ -0,0 +1,940 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use crc32fast::Hasher;
+
+use crate::error::BysqrError;
+use crate::header::Header;
+use crate::lzma::decompress;
+use crate::models::{
+    Amount, BankAccount, BankAccounts, Contact, CustomerParty, Decimal, DeliveryNoteReference, DirectDebitExt,
+    Document, Invoice, InvoiceDocumentType, InvoiceItems, InvoiceLine, InvoiceLines, MonetarySummary, OrderReference,
+    Pay, Payment, PaymentMeans, PaymentOptions, Payments, PostalAddress, SingleInvoiceLine, StandingOrderExt,
+    SupplierParty, TaxCategorySummaries, TaxCategorySummary, DirectDebitScheme, DirectDebitType, Months, Periodicity,
+};
+
+#[derive(Debug, Clone)]
+pub enum DecodeError {
+    /// The input contains a character outside of the base32hex alphabet.
+    InvalidCharacter(char),
+    /// The input is too short to contain header and length of the payload.
+    PayloadTooShort,
+    /// The header describes a document which is not supported by this decoder.
+    UnsupportedDocument { square_type: u8, version: u8, document_type: u8 },
+    /// LZMA decompression of the payload failed.
+    Decompression(String),
+    /// CRC32 checksum stored in the payload does not match its content.
+    ChecksumMismatch { expected: u32, actual: u32 },
+    /// The decompressed sequence is not valid UTF-8 text.
+    InvalidEncoding,
+    /// The sequence ended before a required field could be read.
+    MissingField(&'static str),
+    /// The field contains a value that can not be mapped to the model.
+    InvalidValue { field: &'static str, value: String },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::InvalidCharacter(c) => write!(f, "Decoding error: Invalid character '{}' in input", c),
+            DecodeError::PayloadTooShort => write!(f, "Decoding error: The payload is too short"),
+            DecodeError::UnsupportedDocument { square_type, version, document_type } => write!(
+                f,
+                "Decoding error: Unsupported document (type {}, version {}, document type {})",
+                square_type, version, document_type
+            ),
+            DecodeError::Decompression(reason) => write!(f, "Decoding error: Unable to decompress payload: {}", reason),
+            DecodeError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "Decoding error: Checksum mismatch, expected {:08x} but computed {:08x}",
+                expected, actual
+            ),
+            DecodeError::InvalidEncoding => write!(f, "Decoding error: The payload is not a valid UTF-8 text"),
+            DecodeError::MissingField(field) => write!(f, "Decoding error: Missing value of {}", field),
+            DecodeError::InvalidValue { field, value } => write!(f, "Decoding error: Invalid value {} of {}", value, field),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+pub(crate) fn base32_decode(content: &str) -> Result<Vec<u8>, DecodeError> {
+    content
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '0'..='9' => Ok(c as u8 - b'0'),
+            'A'..='V' => Ok(c as u8 - b'A' + 10),
+            _ => Err(DecodeError::InvalidCharacter(c)),
+        })
+        .collect()
+}
+
+/// Regroup 5-bit values back into bytes. Padding bits added by the encoder are dropped.
+pub(crate) fn regroup_to_bytes(values: &[u8]) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::with_capacity(values.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits: u32 = 0;
+
+    for &value in values {
+        buffer = (buffer << 5) | (value as u32 & 0b11111);
+        bits += 5;
+
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    bytes
+}
+
+fn as_option(value: &str) -> Option<String> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+fn as_date(value: &str, field: &'static str) -> Result<Option<NaiveDate>, DecodeError> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    NaiveDate::parse_from_str(value, "%Y%m%d")
+        .map(Some)
+        .map_err(|_| DecodeError::InvalidValue { field, value: value.to_string() })
+}
+
+/// Parse the value of a field with validated type, such as IBAN or symbols.
+fn as_parsed<T: FromStr>(value: &str, field: &'static str) -> Result<T, DecodeError> {
+    value.parse().map_err(|_| DecodeError::InvalidValue { field, value: value.to_string() })
+}
+
+fn as_optional_parsed<T: FromStr>(value: &str, field: &'static str) -> Result<Option<T>, DecodeError> {
+    if value.is_empty() {
+        Ok(None)
+    } else {
+        as_parsed(value, field).map(Some)
+    }
+}
+
+fn as_payment_options(value: &str) -> Result<PaymentOptions, DecodeError> {
+    value
+        .parse::<u8>()
+        .ok()
+        .and_then(PaymentOptions::from_bits)
+        .ok_or_else(|| DecodeError::InvalidValue { field: "payment options", value: value.to_string() })
+}
+
+/// Reads tab separated fields of the decompressed sequence in order.
+struct Sequence<'a> {
+    fields: std::str::Split<'a, char>,
+}
+
+impl<'a> Sequence<'a> {
+    fn new(seq: &'a str) -> Self {
+        Self { fields: seq.split('\t') }
+    }
+
+    fn next(&mut self, field: &'static str) -> Result<&'a str, DecodeError> {
+        self.fields.next().ok_or(DecodeError::MissingField(field))
+    }
+
+    /// Read a field which was introduced in later versions of the specification,
+    /// hence it may be missing in codes generated by older encoders.
+    fn next_optional(&mut self) -> Option<&'a str> {
+        self.fields.next()
+    }
+
+    fn next_count(&mut self, field: &'static str) -> Result<usize, DecodeError> {
+        let value = self.next(field)?;
+
+        value.parse().map_err(|_| DecodeError::InvalidValue { field, value: value.to_string() })
+    }
+}
+
+fn bank_account_from_seq(seq: &mut Sequence) -> Result<BankAccount, DecodeError> {
+    // IBAN = order 1
+    let iban = as_parsed(seq.next("IBAN")?, "IBAN")?;
+
+    // BIC = order 2
+    let bic = as_optional_parsed(seq.next("BIC")?, "BIC")?;
+
+    Ok(BankAccount { iban, bic })
+}
+
+fn standing_order_ext_from_seq(seq: &mut Sequence) -> Result<StandingOrderExt, DecodeError> {
+    // Day = order 1
+    let day = match seq.next("standing order day")? {
+        "" => None,
+        value => Some(value.parse::<u16>().map_err(|_| DecodeError::InvalidValue {
+            field: "standing order day",
+            value: value.to_string(),
+        })?),
+    };
+
+    // Month = order 2
+    let month = match seq.next("standing order month")? {
+        // Empty mask is written by encoders which do not use the months
+        "" | "0" => None,
+        value => Some(
+            value
+                .parse::<u16>()
+                .ok()
+                .and_then(Months::from_bits)
+                .ok_or_else(|| DecodeError::InvalidValue { field: "standing order month", value: value.to_string() })?,
+        ),
+    };
+
+    // Periodicity = order 3
+    let value = seq.next("periodicity")?;
+    let mut chars = value.chars();
+
+    let periodicity = match (chars.next().and_then(Periodicity::from_code), chars.next()) {
+        (Some(periodicity), None) => periodicity,
+        _ => return Err(DecodeError::InvalidValue { field: "periodicity", value: value.to_string() }),
+    };
+
+    // Last Date = order 4
+    let last_date = as_date(seq.next("standing order last date")?, "standing order last date")?;
+
+    Ok(StandingOrderExt { day, month, periodicity, last_date })
+}
+
+fn as_amount(value: &str, field: &'static str) -> Result<Option<Amount>, DecodeError> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    value
+        .parse::<Amount>()
+        .map(Some)
+        .map_err(|_| DecodeError::InvalidValue { field, value: value.to_string() })
+}
+
+/// Read the code of a code list, such as the direct debit scheme.
+fn as_code<T>(value: &str, from_code: fn(u8) -> Option<T>, field: &'static str) -> Result<T, DecodeError> {
+    value
+        .parse::<u8>()
+        .ok()
+        .and_then(from_code)
+        .ok_or_else(|| DecodeError::InvalidValue { field, value: value.to_string() })
+}
+
+fn direct_debit_ext_from_seq(seq: &mut Sequence) -> Result<DirectDebitExt, DecodeError> {
+    // Direct Debit Scheme = order 1
+    let direct_debit_scheme = as_code(seq.next("direct debit scheme")?, DirectDebitScheme::from_code, "direct debit scheme")?;
+
+    // Direct Debit Type = order 2
+    let direct_debit_type = as_code(seq.next("direct debit type")?, DirectDebitType::from_code, "direct debit type")?;
+
+    // Variable Symbol = order 3
+    let variable_symbol = as_optional_parsed(seq.next("direct debit variable symbol")?, "direct debit variable symbol")?;
+
+    // Specific Symbol = order 4
+    let specific_symbol = as_optional_parsed(seq.next("direct debit specific symbol")?, "direct debit specific symbol")?;
+
+    // Originators Reference Information = order 5
+    let originators_reference_information = as_option(seq.next("direct debit originators reference information")?);
+
+    // Mandate ID = order 6
+    let mandate_id = as_option(seq.next("mandate id")?);
+
+    // Creditor ID = order 7
+    let creditor_id = as_option(seq.next("creditor id")?);
+
+    // Contract ID = order 8
+    let contract_id = as_option(seq.next("contract id")?);
+
+    // Max Amount = order 9
+    let max_amount = as_amount(seq.next("direct debit max amount")?, "direct debit max amount")?;
+
+    // Valid Till Date = order 10
+    let valid_till_date = as_date(seq.next("direct debit valid till date")?, "direct debit valid till date")?;
+
+    Ok(DirectDebitExt {
+        direct_debit_scheme,
+        direct_debit_type,
+        variable_symbol,
+        specific_symbol,
+        originators_reference_information,
+        mandate_id,
+        creditor_id,
+        contract_id,
+        max_amount,
+        valid_till_date,
+    })
+}
+
+/// Convert sequence to Payment.
+fn payment_from_seq(seq: &mut Sequence) -> Result<Payment, DecodeError> {
+    // PaymentOptions = order 1
+    let payment_options = as_payment_options(seq.next("payment options")?)?;
+
+    // Amount = order 2
+    let amount = as_amount(seq.next("amount")?, "amount")?;
+
+    // Currency = order 3
+    let currency_code = as_parsed(seq.next("currency code")?, "currency code")?;
+
+    // Payment due date = order 4
+    let payment_due_date = as_date(seq.next("payment due date")?, "payment due date")?;
+
+    // Variable Symbol = order 5
+    let variable_symbol = as_optional_parsed(seq.next("variable symbol")?, "variable symbol")?;
+
+    // Constant Symbol = order 6
+    let constant_symbol = as_optional_parsed(seq.next("constant symbol")?, "constant symbol")?;
+
+    // Specific Symbol = order 7
+    let specific_symbol = as_optional_parsed(seq.next("specific symbol")?, "specific symbol")?;
+
+    // Originators Reference Information = order 8
+    let originators_reference_information = as_option(seq.next("originators reference information")?);
+
+    // Payment Note = order 9
+    let payment_note = as_option(seq.next("payment note")?);
+
+    // Bank Accounts = order 10
+    let mut bank_account: Vec<BankAccount> = Vec::new();
+    for _ in 0..seq.next_count("bank accounts")? {
+        bank_account.push(bank_account_from_seq(seq)?);
+    }
+
+    // Standing Order Extension = order 11
+    let standing_order_ext = match seq.next("standing order extension")? {
+        "0" => None,
+        "1" => Some(standing_order_ext_from_seq(seq)?),
+        value => return Err(DecodeError::InvalidValue {
+            field: "standing order extension",
+            value: value.to_string(),
+        }),
+    };
+
+    // Direct Debit Extension = order 12
+    let direct_debit_ext = match seq.next("direct debit extension")? {
+        "0" => None,
+        "1" => Some(direct_debit_ext_from_seq(seq)?),
+        value => return Err(DecodeError::InvalidValue {
+            field: "direct debit extension",
+            value: value.to_string(),
+        }),
+    };
+
+    // Beneficiary Name = order 13
+    let beneficiary_name = seq.next_optional().and_then(as_option);
+
+    // Beneficiary Address Line 1 = order 14
+    let beneficiary_address_line_1 = seq.next_optional().and_then(as_option);
+
+    // Beneficiary Address Line 2 = order 15
+    let beneficiary_address_line_2 = seq.next_optional().and_then(as_option);
+
+    Ok(Payment {
+        payment_options,
+        amount,
+        currency_code,
+        payment_due_date,
+        variable_symbol,
+        constant_symbol,
+        specific_symbol,
+        originators_reference_information,
+        payment_note,
+        bank_accounts: BankAccounts { bank_account },
+        standing_order_ext,
+        direct_debit_ext,
+        beneficiary_name,
+        beneficiary_address_line_1,
+        beneficiary_address_line_2,
+    })
+}
+
+fn as_decimal(value: &str, field: &'static str) -> Result<Option<Decimal>, DecodeError> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    value
+        .parse::<Decimal>()
+        .map(Some)
+        .map_err(|_| DecodeError::InvalidValue { field, value: value.to_string() })
+}
+
+fn as_required<T>(value: Option<T>, field: &'static str) -> Result<T, DecodeError> {
+    value.ok_or(DecodeError::MissingField(field))
+}
+
+fn supplier_party_from_seq(seq: &mut Sequence) -> Result<SupplierParty, DecodeError> {
+    // Party Name = order 1
+    let party_name = seq.next("supplier party name")?.to_string();
+
+    // Company Tax ID = order 2
+    let company_tax_id = as_option(seq.next("supplier company tax id")?);
+
+    // Company VAT ID = order 3
+    let company_vat_id = as_option(seq.next("supplier company vat id")?);
+
+    // Company Register ID = order 4
+    let company_register_id = as_option(seq.next("supplier company register id")?);
+
+    // Postal Address = order 5
+    let postal_address = PostalAddress {
+        street_name: seq.next("street name")?.to_string(),
+        building_number: as_option(seq.next("building number")?),
+        city_name: seq.next("city name")?.to_string(),
+        postal_zone: seq.next("postal zone")?.to_string(),
+        state: as_option(seq.next("state")?),
+        country: as_parsed(seq.next("country")?, "country")?,
+    };
+
+    // Contact = order 6, missing when all its fields are empty
+    let contact = Contact {
+        name: as_option(seq.next("contact name")?),
+        telephone: as_option(seq.next("contact telephone")?),
+        email: as_option(seq.next("contact email")?),
+    };
+
+    let contact = if contact.name.is_none() && contact.telephone.is_none() && contact.email.is_none() {
+        None
+    } else {
+        Some(contact)
+    };
+
+    Ok(SupplierParty { party_name, company_tax_id, company_vat_id, company_register_id, postal_address, contact })
+}
+
+fn customer_party_from_seq(seq: &mut Sequence) -> Result<CustomerParty, DecodeError> {
+    // Party Name = order 1
+    let party_name = seq.next("customer party name")?.to_string();
+
+    // Company Tax ID = order 2
+    let company_tax_id = as_option(seq.next("customer company tax id")?);
+
+    // Company VAT ID = order 3
+    let company_vat_id = as_option(seq.next("customer company vat id")?);
+
+    // Company Register ID = order 4
+    let company_register_id = as_option(seq.next("customer company register id")?);
+
+    // Party Identification = order 5
+    let party_identification = as_option(seq.next("party identification")?);
+
+    Ok(CustomerParty { party_name, company_tax_id, company_vat_id, company_register_id, party_identification })
+}
+
+/// Read the single invoice line, which is None when all its fields are empty.
+fn single_invoice_line_from_seq(seq: &mut Sequence) -> Result<Option<SingleInvoiceLine>, DecodeError> {
+    // Order Line ID = order 1
+    let order_line_id = as_option(seq.next("order line id")?);
+
+    // Delivery Note Line ID = order 2
+    let delivery_note_line_id = as_option(seq.next("delivery note line id")?);
+
+    // Item Name = order 3
+    let item_name = as_option(seq.next("item name")?);
+
+    // Item EAN Code = order 4
+    let item_ean_code = as_option(seq.next("item ean code")?);
+
+    // Period From Date = order 5
+    let period_from_date = as_date(seq.next("period from date")?, "period from date")?;
+
+    // Period To Date = order 6
+    let period_to_date = as_date(seq.next("period to date")?, "period to date")?;
+
+    // Invoiced Quantity = order 7
+    let invoiced_quantity = as_decimal(seq.next("invoiced quantity")?, "invoiced quantity")?;
+
+    if order_line_id.is_none()
+        && delivery_note_line_id.is_none()
+        && item_name.is_none()
+        && item_ean_code.is_none()
+        && period_from_date.is_none()
+        && period_to_date.is_none()
+        && invoiced_quantity.is_none()
+    {
+        return Ok(None);
+    }
+
+    Ok(Some(SingleInvoiceLine {
+        order_line_id,
+        delivery_note_line_id,
+        item_name,
+        item_ean_code,
+        period_from_date,
+        period_to_date,
+        invoiced_quantity: as_required(invoiced_quantity, "invoiced quantity")?,
+    }))
+}
+
+fn tax_category_summary_from_seq(seq: &mut Sequence) -> Result<TaxCategorySummary, DecodeError> {
+    // Classified Tax Category = order 1
+    let classified_tax_category = as_decimal(seq.next("classified tax category")?, "classified tax category")?;
+
+    // Tax Exclusive Amount = order 2
+    let tax_exclusive_amount = as_amount(seq.next("tax exclusive amount")?, "tax exclusive amount")?;
+
+    // Tax Amount = order 3
+    let tax_amount = as_amount(seq.next("tax amount")?, "tax amount")?;
+
+    // Already Claimed Tax Exclusive Amount = order 4
+    let already_claimed_tax_exclusive_amount = as_amount(
+        seq.next("already claimed tax exclusive amount")?,
+        "already claimed tax exclusive amount",
+    )?;
+
+    // Already Claimed Tax Amount = order 5
+    let already_claimed_tax_amount = as_amount(seq.next("already claimed tax amount")?, "already claimed tax amount")?;
+
+    Ok(TaxCategorySummary {
+        classified_tax_category: as_required(classified_tax_category, "classified tax category")?,
+        tax_exclusive_amount: as_required(tax_exclusive_amount, "tax exclusive amount")?,
+        tax_amount: as_required(tax_amount, "tax amount")?,
+        already_claimed_tax_exclusive_amount: as_required(already_claimed_tax_exclusive_amount, "already claimed tax exclusive amount")?,
+        already_claimed_tax_amount: as_required(already_claimed_tax_amount, "already claimed tax amount")?,
+    })
+}
+
+/// Convert sequence to Invoice of the document type read from the header.
+fn invoice_from_seq(seq: &mut Sequence, document_type: InvoiceDocumentType) -> Result<Invoice, DecodeError> {
+    // InvoiceID = order 1
+    let invoice_id = seq.next("invoice id")?.to_string();
+
+    // Issue Date = order 2
+    let issue_date = as_required(as_date(seq.next("issue date")?, "issue date")?, "issue date")?;
+
+    // Tax Point Date = order 3
+    let tax_point_date = as_date(seq.next("tax point date")?, "tax point date")?;
+
+    // Order ID = order 4
+    let order_id = as_option(seq.next("order id")?);
+
+    // Delivery Note ID = order 5
+    let delivery_note_id = as_option(seq.next("delivery note id")?);
+
+    // Local Currency Code = order 6
+    let local_currency_code = as_parsed(seq.next("local currency code")?, "local currency code")?;
+
+    // Foreign Currency Code = order 7
+    let foreign_currency_code = as_optional_parsed(seq.next("foreign currency code")?, "foreign currency code")?;
+
+    // Curr Rate = order 8
+    let curr_rate = as_decimal(seq.next("curr rate")?, "curr rate")?;
+
+    // Reference Curr Rate = order 9
+    let reference_curr_rate = as_decimal(seq.next("reference curr rate")?, "reference curr rate")?;
+
+    // Supplier Party = order 10
+    let supplier_party = supplier_party_from_seq(seq)?;
+
+    // Customer Party = order 11
+    let customer_party = customer_party_from_seq(seq)?;
+
+    // Number Of Invoice Lines = order 12
+    let number_of_invoice_lines = match seq.next("number of invoice lines")? {
+        "" => None,
+        value => Some(value.parse::<u32>().map_err(|_| DecodeError::InvalidValue {
+            field: "number of invoice lines",
+            value: value.to_string(),
+        })?),
+    };
+
+    // Invoice Description = order 13
+    let invoice_description = as_option(seq.next("invoice description")?);
+
+    // Single Invoice Line = order 14
+    let single_invoice_line = single_invoice_line_from_seq(seq)?;
+
+    // Tax Category Summaries = order 15
+    let mut tax_category_summary: Vec<TaxCategorySummary> = Vec::new();
+    for _ in 0..seq.next_count("tax category summaries")? {
+        tax_category_summary.push(tax_category_summary_from_seq(seq)?);
+    }
+
+    // Monetary Summary = order 16
+    let payable_rounding_amount = as_amount(seq.next("payable rounding amount")?, "payable rounding amount")?;
+    let paid_deposits_amount = as_amount(seq.next("paid deposits amount")?, "paid deposits amount")?;
+
+    // Payment Means = order 17
+    let payment_means = match seq.next("payment means")? {
+        "" => None,
+        value => Some(
+            value
+                .parse::<u8>()
+                .ok()
+                .and_then(PaymentMeans::from_bits)
+                .ok_or_else(|| DecodeError::InvalidValue { field: "payment means", value: value.to_string() })?,
+        ),
+    };
+
+    Ok(Invoice {
+        document_type,
+        invoice_id,
+        issue_date,
+        tax_point_date,
+        order_id,
+        delivery_note_id,
+        local_currency_code,
+        foreign_currency_code,
+        curr_rate,
+        reference_curr_rate,
+        supplier_party,
+        customer_party,
+        number_of_invoice_lines,
+        invoice_description,
+        single_invoice_line,
+        tax_category_summaries: TaxCategorySummaries { tax_category_summary },
+        monetary_summary: MonetarySummary {
+            payable_rounding_amount: as_required(payable_rounding_amount, "payable rounding amount")?,
+            paid_deposits_amount: as_required(paid_deposits_amount, "paid deposits amount")?,
+        },
+        payment_means,
+    })
+}
+
+fn invoice_line_from_seq(seq: &mut Sequence) -> Result<InvoiceLine, DecodeError> {
+    // Order Reference = order 1, missing when both its fields are empty
+    let order_id = as_option(seq.next("order id")?);
+    let order_line_id = as_option(seq.next("order line id")?);
+
+    let order_reference = if order_id.is_none() && order_line_id.is_none() {
+        None
+    } else {
+        Some(OrderReference { order_id, order_line_id })
+    };
+
+    // Delivery Note Reference = order 2, missing when both its fields are empty
+    let delivery_note_id = as_option(seq.next("delivery note id")?);
+    let delivery_note_line_id = as_option(seq.next("delivery note line id")?);
+
+    let delivery_note_reference = if delivery_note_id.is_none() && delivery_note_line_id.is_none() {
+        None
+    } else {
+        Some(DeliveryNoteReference { delivery_note_id, delivery_note_line_id })
+    };
+
+    // Item Name = order 3
+    let item_name = as_option(seq.next("item name")?);
+
+    // Item EAN Code = order 4
+    let item_ean_code = as_option(seq.next("item ean code")?);
+
+    // Period From Date = order 5
+    let period_from_date = as_date(seq.next("period from date")?, "period from date")?;
+
+    // Period To Date = order 6
+    let period_to_date = as_date(seq.next("period to date")?, "period to date")?;
+
+    // Invoiced Quantity = order 7
+    let invoiced_quantity = as_decimal(seq.next("invoiced quantity")?, "invoiced quantity")?;
+
+    // Unit Price Tax Exclusive Amount = order 8
+    let unit_price_tax_exclusive_amount = as_decimal(
+        seq.next("unit price tax exclusive amount")?,
+        "unit price tax exclusive amount",
+    )?;
+
+    // Unit Price Tax Amount = order 9
+    let unit_price_tax_amount = as_decimal(seq.next("unit price tax amount")?, "unit price tax amount")?;
+
+    // Classified Tax Category = order 10
+    let classified_tax_category = as_decimal(seq.next("classified tax category")?, "classified tax category")?;
+
+    Ok(InvoiceLine {
+        order_reference,
+        delivery_note_reference,
+        item_name,
+        item_ean_code,
+        period_from_date,
+        period_to_date,
+        invoiced_quantity: as_required(invoiced_quantity, "invoiced quantity")?,
+        unit_price_tax_exclusive_amount: as_required(unit_price_tax_exclusive_amount, "unit price tax exclusive amount")?,
+        unit_price_tax_amount: as_required(unit_price_tax_amount, "unit price tax amount")?,
+        classified_tax_category: as_required(classified_tax_category, "classified tax category")?,
+    })
+}
+
+fn invoice_items_from_seq(seq: &mut Sequence) -> Result<InvoiceItems, DecodeError> {
+    // InvoiceID = order 1
+    let invoice_id = seq.next("invoice id")?.to_string();
+
+    // First Invoice Line ID = order 2
+    let first_invoice_line_id = seq.next("first invoice line id")?.to_string();
+
+    // Invoice Lines = order 3
+    let mut invoice_line: Vec<InvoiceLine> = Vec::new();
+    for _ in 0..seq.next_count("invoice lines")? {
+        invoice_line.push(invoice_line_from_seq(seq)?);
+    }
+
+    Ok(InvoiceItems { invoice_id, first_invoice_line_id, invoice_lines: InvoiceLines { invoice_line } })
+}
+
+/// Decode the header and decompress the sequence, verifying its checksum.
+fn decode_payload(content: &str) -> Result<(Header, String), DecodeError> {
+    let payload = regroup_to_bytes(&base32_decode(content)?);
+
+    if payload.len() < 4 {
+        return Err(DecodeError::PayloadTooShort);
+    }
+
+    let header = Header::from_bytes([payload[0], payload[1]])?;
+
+    let size = u16::from_le_bytes([payload[2], payload[3]]) as usize;
+    let decompressed = decompress(&payload[4..], size).map_err(|e| DecodeError::Decompression(e.to_string()))?;
+
+    if decompressed.len() < 4 {
+        return Err(DecodeError::PayloadTooShort);
+    }
+
+    let expected = u32::from_le_bytes([decompressed[0], decompressed[1], decompressed[2], decompressed[3]]);
+
+    let mut hasher = Hasher::new();
+    hasher.update(&decompressed[4..]);
+    let actual = hasher.finalize();
+
+    if expected != actual {
+        return Err(DecodeError::ChecksumMismatch { expected, actual });
+    }
+
+    let seq = String::from_utf8(decompressed[4..].to_vec()).map_err(|_| DecodeError::InvalidEncoding)?;
+
+    Ok((header, seq))
+}
+
+fn pay_from_seq(seq: &mut Sequence) -> Result<Pay, DecodeError> {
+    // InvoiceID = order 1
+    let invoice_id = as_option(seq.next("invoice id")?);
+
+    // Payments = order 2
+    let mut payment: Vec<Payment> = Vec::new();
+    for _ in 0..seq.next_count("payments")? {
+        payment.push(payment_from_seq(seq)?);
+    }
+
+    Ok(Pay { invoice_id, payments: Payments { payment } })
+}
+
+pub fn decode(content: &str) -> Result<Pay, BysqrError> {
+    let (header, seq) = decode_payload(content)?;
+
+    if header.square_type != 0 || header.document_type != 0 {
+        return Err(header.unsupported().into());
+    }
+
+    Ok(pay_from_seq(&mut Sequence::new(&seq))?)
+}
+
+/// Decode INVOICE by square content. The type of the invoice is read from the header.
+pub fn decode_invoice(content: &str) -> Result<Invoice, BysqrError> {
+    let (header, seq) = decode_payload(content)?;
+
+    let document_type = match (header.square_type, InvoiceDocumentType::from_code(header.document_type)) {
+        (1, Some(document_type)) => document_type,
+        _ => return Err(header.unsupported().into()),
+    };
+
+    Ok(invoice_from_seq(&mut Sequence::new(&seq), document_type)?)
+}
+
+/// Decode INVOICE by square content with the list of invoice items.
+pub fn decode_invoice_items(content: &str) -> Result<InvoiceItems, BysqrError> {
+    let (header, seq) = decode_payload(content)?;
+
+    if header.square_type != 2 || header.document_type != 0 {
+        return Err(header.unsupported().into());
+    }
+
+    Ok(invoice_items_from_seq(&mut Sequence::new(&seq))?)
+}
+
+/// Decode content of any supported by square type, as detected from the header.
+pub fn decode_document(content: &str) -> Result<Document, BysqrError> {
+    let (header, seq) = decode_payload(content)?;
+    let mut seq = Sequence::new(&seq);
+
+    match (header.square_type, header.document_type) {
+        (0, 0) => Ok(Document::Pay(pay_from_seq(&mut seq)?)),
+        (1, code) => match InvoiceDocumentType::from_code(code) {
+            Some(document_type) => Ok(Document::Invoice(Box::new(invoice_from_seq(&mut seq, document_type)?))),
+            None => Err(header.unsupported().into()),
+        },
+        (2, 0) => Ok(Document::InvoiceItems(invoice_items_from_seq(&mut seq)?)),
+        _ => Err(header.unsupported().into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::{base32_decode, decode, decode_payload, regroup_to_bytes, DecodeError};
+    use crate::encoder::{encode, encode_invoice, pay_to_seq};
+    use crate::error::{BysqrError, Rule};
+    use crate::models::{
+        try_deserialize_invoice, try_deserialize_pay, Amount, DirectDebitExt, DirectDebitScheme, DirectDebitType, Month,
+        Months, Pay, PaymentOptions, Periodicity, StandingOrderExt,
+    };
+
+    const PAYMENT: &str = include_str!("../example/payment.xml");
+    const CONTENT: &str = "0005M000B8EL2I2FP2PSOB3ENDMC9PB3SESUJ694JE1139U2J1Q25BF5OQ2D1CB3RFMNVV4J4VVG5IKNTC41O399RIJH2DHL5SIKOUR5JPCOAQDROPLVVVR5VT000";
+
+    fn payment() -> Pay {
+        try_deserialize_pay(PAYMENT).unwrap()
+    }
+
+    fn standing_order(ext: StandingOrderExt) -> Pay {
+        let mut pay = payment();
+        pay.payments.payment[0].payment_options = PaymentOptions::STANDING_ORDER;
+        pay.payments.payment[0].standing_order_ext = Some(ext);
+        pay
+    }
+
+    fn direct_debit(ext: DirectDebitExt) -> Pay {
+        let mut pay = payment();
+        pay.payments.payment[0].payment_options = PaymentOptions::DIRECT_DEBIT;
+        pay.payments.payment[0].direct_debit_ext = Some(ext);
+        pay
+    }
+
+    #[test]
+    fn decodes_known_content() {
+        assert_eq!(decode(CONTENT).unwrap(), payment());
+    }
+
+    #[test]
+    fn encodes_known_content() {
+        assert_eq!(encode(&payment()).unwrap(), CONTENT);
+    }
+
+    #[test]
+    fn decodes_encoded_pay() {
+        let mut pay = payment();
+        pay.invoice_id = Some(String::from("2024/001"));
+        pay.payments.payment.push(pay.payments.payment[0].clone());
+        pay.payments.payment[1].payment_note = Some(String::from("Záloha za služby"));
+
+        assert_eq!(decode(&encode(&pay).unwrap()).unwrap(), pay);
+    }
+
+    #[test]
+    fn decodes_payload_longer_than_255_bytes() {
+        let mut pay = payment();
+        pay.payments.payment[0].payment_note = Some("x".repeat(140));
+        pay.payments.payment.push(pay.payments.payment[0].clone());
+
+        let encoded = encode(&pay).unwrap();
+        let (_, seq) = decode_payload(&encoded).unwrap();
+        let payload = regroup_to_bytes(&base32_decode(&encoded).unwrap());
+        let size = u16::from_le_bytes([payload[2], payload[3]]) as usize;
+
+        assert!(size > 255);
+        assert_eq!(size, seq.len() + 4);
+        assert_eq!(decode(&encoded).unwrap(), pay);
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        assert!(matches!(decode("0005M0w"), Err(BysqrError::Decode(DecodeError::InvalidCharacter('w')))));
+        assert!(matches!(decode("0005"), Err(BysqrError::Decode(DecodeError::PayloadTooShort))));
+    }
+
+    #[test]
+    fn rejects_other_documents() {
+        let invoice = try_deserialize_invoice(include_str!("../example/invoice.xml")).unwrap();
+
+        assert!(matches!(decode(&encode_invoice(&invoice).unwrap()), Err(BysqrError::Decode(DecodeError::UnsupportedDocument { .. }))));
+    }
+
+    #[test]
+    fn decodes_encoded_standing_order() {
+        let months = Months::from(Month::January) | Month::April.into() | Month::July.into() | Month::October.into();
+        assert_eq!(months.bits(), 0b10_0100_1001);
+
+        for periodicity in Periodicity::ALL.iter().copied() {
+            assert_eq!(Periodicity::from_code(periodicity.code()), Some(periodicity));
+
+            for day in [1, periodicity.max_day()] {
+                let pay = standing_order(StandingOrderExt {
+                    day: Some(day),
+                    month: Some(months),
+                    periodicity,
+                    last_date: NaiveDate::from_ymd_opt(2025, 12, 31),
+                });
+
+                assert_eq!(decode(&encode(&pay).unwrap()).unwrap(), pay);
+            }
+        }
+
+        let codes: String = Periodicity::ALL.iter().map(|periodicity| periodicity.code()).collect();
+        assert_eq!(codes, "dwbmBqas");
+    }
+
+    #[test]
+    fn rejects_standing_order_day_out_of_range() {
+        for (periodicity, day) in [(Periodicity::Monthly, 0), (Periodicity::Weekly, 8), (Periodicity::Monthly, 32), (Periodicity::Annually, 367)] {
+            let pay = standing_order(StandingOrderExt { day: Some(day), month: None, periodicity, last_date: None });
+
+            match encode(&pay) {
+                Err(BysqrError::InvalidField { path, rule, .. }) => {
+                    assert_eq!(path, "Payments.Payment[0].StandingOrderExt.Day");
+                    assert_eq!(rule, Rule::Range(1, periodicity.max_day() as u32));
+                }
+                other => panic!("day {} of {} should be rejected, got {:?}", day, periodicity, other),
+            }
+        }
+    }
+
+    #[test]
+    fn decodes_encoded_direct_debit() {
+        let pay = direct_debit(DirectDebitExt {
+            direct_debit_scheme: DirectDebitScheme::Other,
+            direct_debit_type: DirectDebitType::Recurrent,
+            mandate_id: Some(String::from("MANDATE-2024-001")),
+            creditor_id: Some(String::from("SK52ZZZ70000000022")),
+            max_amount: Some("150.5".parse().unwrap()),
+            valid_till_date: NaiveDate::from_ymd_opt(2026, 6, 30),
+            ..DirectDebitExt::default()
+        });
+
+        assert_eq!(decode(&encode(&pay).unwrap()).unwrap(), pay);
+    }
+
+    #[test]
+    fn enforces_direct_debit_rules() {
+        let errors = |pay: &Pay| -> Vec<(String, Rule)> {
+            let mut errors: Vec<BysqrError> = Vec::new();
+            pay_to_seq(pay, &mut errors);
+
+            errors
+                .into_iter()
+                .map(|error| match error {
+                    BysqrError::InvalidField { path, rule, .. } => (path, rule),
+                    other => panic!("unexpected error: {}", other),
+                })
+                .collect()
+        };
+
+        let mut pay = direct_debit(DirectDebitExt {
+            variable_symbol: Some("123".parse().unwrap()),
+            mandate_id: Some(String::from("MANDATE")),
+            creditor_id: Some(String::from("CREDITOR")),
+            max_amount: Some(Amount::from_minor_units(-100)),
+            ..DirectDebitExt::default()
+        });
+        let path = "Payments.Payment[0].DirectDebitExt";
+
+        assert_eq!(errors(&pay), [
+            (format!("{}.MandateID", path), Rule::Exclusive("VariableSymbol")),
+            (format!("{}.CreditorID", path), Rule::Exclusive("VariableSymbol")),
+            (format!("{}.MaxAmount", path), Rule::Positive),
+        ]);
+
+        pay.payments.payment[0].payment_options = PaymentOptions::PAYMENT_ORDER;
+        assert_eq!(errors(&pay), [(path.to_string(), Rule::PaymentOption("directdebit"))]);
+    }
+}
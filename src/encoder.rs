@@ -1,157 +1,617 @@
-use std::io::Write;
-
 use chrono::NaiveDate;
 use crc32fast::Hasher;
 use regex::Regex;
-use liblzma::stream::{LzmaOptions, Stream};
-use liblzma::write::XzEncoder;
 
-use crate::models::{BankAccount, Pay, Payment};
+use crate::models::{
+    Amount, BankAccount, CustomerParty, Decimal, DirectDebitExt, Invoice, InvoiceItems, InvoiceLine, Pay, Payment,
+    PaymentOption, PostalAddress, SingleInvoiceLine, StandingOrderExt, SupplierParty, TaxCategorySummary,
+};
+use crate::control::{sanitize_invoice, sanitize_invoice_items, sanitize_pay, ControlCharacters};
+use crate::error::{BysqrError, Rule};
+use crate::lzma::compress;
+use crate::header::{Header, Version};
+use crate::truncation::{truncate, truncate_invoice, truncate_invoice_items, TruncationLimit, TruncationReport};
+
+#[derive(Debug, Clone, Default)]
+pub struct EncodeOptions {
+    /// When set, fields are shortened in ascending order of their priority
+    /// until the encoded content fits into the limit.
+    pub truncate: Option<TruncationLimit>,
+    /// Version of the specification written into the header.
+    pub version: Version,
+    /// Handling of tabs and other control characters in values of fields.
+    pub control_characters: ControlCharacters,
+}
 
-fn as_pattern_str(value: &Option<String>, pattern: &str, description: &str) -> String {
+fn as_pattern_str(value: &Option<String>, pattern: &'static str, path: &str) -> Result<String, BysqrError> {
     if let Some(val) = value {
         if Regex::new(pattern).unwrap().is_match(val) {
-            val.clone()
+            Ok(val.clone())
         } else {
-            panic!("Encoding error: The {} does not match pattern {}", description, pattern);
+            Err(BysqrError::invalid_field(path, val, Rule::Pattern(pattern)))
         }
     } else {
-        String::new()
+        Ok(String::new())
     }
 }
 
-fn as_valid_date(value: &String) -> String {
-    if Regex::new(r"\d{4}-\d{2}-\d{2}").unwrap().is_match(value) {
-        if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
-            date.format("%Y%m%d").to_string()
-        } else {
-            panic!("Encoding error: The date is not valid {}", value)
-        }
-    } else if Regex::new(r"\d{4}\d{2}\d{2}").unwrap().is_match(value) {
-        if let Ok(_) = NaiveDate::parse_from_str(value, "%Y%m%d") {
-            value.clone()
-        } else {
-            panic!("Encoding error: The date is not valid {}", value)
+fn as_amount_str(value: Option<Amount>, path: &str) -> Result<String, BysqrError> {
+    match value {
+        None => Ok(String::new()),
+        Some(value) => {
+            if value.is_negative() {
+                return Err(BysqrError::invalid_field(path, &value.to_string(), Rule::Positive));
+            }
+
+            Ok(value.to_string())
         }
-    } else {
-        panic!("Encoding error: Invalid date format {}", value)
     }
 }
 
-fn as_decimal_str(decimal: f32) -> String {
-    let as_str = format!("{:.2}", decimal);
+/// Value of a field with validated type, or empty when missing.
+fn as_optional_value<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(|value| value.to_string()).unwrap_or_default()
+}
 
-    if let Some(_) = as_str.find('.') {
-        let mut truncated = as_str;
+fn as_date_str(value: &Option<NaiveDate>) -> String {
+    value.map(|date| date.format("%Y%m%d").to_string()).unwrap_or_default()
+}
 
-        while truncated.ends_with('0') {
-            truncated.pop();
-        }
+/// Unwrap the checked value, or record the error and continue with an empty value,
+/// so the rest of the document is still checked.
+fn check<T: Default>(result: Result<T, BysqrError>, errors: &mut Vec<BysqrError>) -> T {
+    result.unwrap_or_else(|error| {
+        errors.push(error);
+        T::default()
+    })
+}
 
-        if truncated.ends_with('.') {
-            truncated.pop();
-        }
+fn bank_account_to_seq(bank_account: &BankAccount) -> Vec<String> {
+    vec![
+        // IBAN = order 1
+        bank_account.iban.to_string(),
+        // BIC = order 2
+        as_optional_value(&bank_account.bic),
+    ]
+}
 
-        truncated
-    } else {
-        as_str
+fn standing_order_ext_to_seq(ext: &StandingOrderExt, path: &str, errors: &mut Vec<BysqrError>) -> Vec<String> {
+    let mut seq: Vec<String> = Vec::new();
+
+    // Day = order 1, its range depends on the periodicity
+    if let Some(day) = ext.day {
+        let max_day = ext.periodicity.max_day();
+
+        if !(1..=max_day).contains(&day) {
+            errors.push(BysqrError::invalid_field(&format!("{}.Day", path), &day.to_string(), Rule::Range(1, max_day as u32)));
+        }
     }
+
+    seq.push(as_optional_value(&ext.day));
+
+    // Month = order 2
+    seq.push(ext.month.map(|month| format!("{}", month.bits())).unwrap_or_default());
+
+    // Periodicity = order 3
+    seq.push(ext.periodicity.code().to_string());
+
+    // Last Date = order 4
+    seq.push(as_date_str(&ext.last_date));
+
+    seq
 }
 
-fn bank_account_to_seq(bank_account: &BankAccount) -> Vec<String> {
-    let mut seq: Vec<String> = Vec::new();
+/// Check the choice of the XSD, the payment is identified either by (VariableSymbol, SpecificSymbol),
+/// by OriginatorsReferenceInformation, or by (MandateID, CreditorID, ContractID). Each field of a later
+/// group is reported together with the first field of the group which was used.
+fn check_direct_debit_choice(ext: &DirectDebitExt, path: &str, errors: &mut Vec<BysqrError>) {
+    let groups: [Vec<(&'static str, Option<String>)>; 3] = [
+        vec![
+            ("VariableSymbol", as_optional_string(&ext.variable_symbol)),
+            ("SpecificSymbol", as_optional_string(&ext.specific_symbol)),
+        ],
+        vec![("OriginatorsReferenceInformation", ext.originators_reference_information.clone())],
+        vec![
+            ("MandateID", ext.mandate_id.clone()),
+            ("CreditorID", ext.creditor_id.clone()),
+            ("ContractID", ext.contract_id.clone()),
+        ],
+    ];
 
-    // IBAN = order 1
-    if Regex::new(r"^[A-Z]{2}\d{2}[A-Z\d]{0,30}$").unwrap().is_match(&bank_account.iban) {
-        seq.push(bank_account.iban.clone())
-    } else {
-        panic!("Encoding error: IBAN does not have valid format")
+    let mut chosen: Option<&'static str> = None;
+
+    for group in groups {
+        let present: Vec<(&'static str, String)> =
+            group.into_iter().filter_map(|(name, value)| value.map(|value| (name, value))).collect();
+
+        match (chosen, present.first()) {
+            (None, Some((name, _))) => chosen = Some(name),
+            (Some(other), Some(_)) => {
+                for (name, value) in present {
+                    errors.push(BysqrError::invalid_field(&format!("{}.{}", path, name), &value, Rule::Exclusive(other)));
+                }
+            }
+            _ => {}
+        }
     }
+}
+
+fn as_optional_string<T: ToString>(value: &Option<T>) -> Option<String> {
+    value.as_ref().map(|value| value.to_string())
+}
+
+fn direct_debit_ext_to_seq(ext: &DirectDebitExt, path: &str, errors: &mut Vec<BysqrError>) -> Vec<String> {
+    check_direct_debit_choice(ext, path, errors);
+
+    let mut seq: Vec<String> = vec![
+        // Direct Debit Scheme = order 1
+        format!("{}", ext.direct_debit_scheme.code()),
+        // Direct Debit Type = order 2
+        format!("{}", ext.direct_debit_type.code()),
+    ];
+
+    // Variable Symbol = order 3
+    seq.push(as_optional_value(&ext.variable_symbol));
+
+    // Specific Symbol = order 4
+    seq.push(as_optional_value(&ext.specific_symbol));
+
+    // Originators Reference Information = order 5
+    seq.push(check(as_pattern_str(&ext.originators_reference_information, r"^.{0,35}$", &format!("{}.OriginatorsReferenceInformation", path)), errors));
+
+    // Mandate ID = order 6
+    seq.push(check(as_pattern_str(&ext.mandate_id, r"^.{0,35}$", &format!("{}.MandateID", path)), errors));
+
+    // Creditor ID = order 7
+    seq.push(check(as_pattern_str(&ext.creditor_id, r"^.{0,35}$", &format!("{}.CreditorID", path)), errors));
+
+    // Contract ID = order 8
+    seq.push(check(as_pattern_str(&ext.contract_id, r"^.{0,35}$", &format!("{}.ContractID", path)), errors));
+
+    // Max Amount = order 9
+    seq.push(check(as_amount_str(ext.max_amount, &format!("{}.MaxAmount", path)), errors));
 
-    // BIC = order 2
-    seq.push(as_pattern_str(&bank_account.bic, r"^[A-Z]{4}[A-Z]{2}[A-Z\d]{2}([A-Z\d]{3})?$", "BIC"));
+    // Valid Till Date = order 10
+    seq.push(as_date_str(&ext.valid_till_date));
 
     seq
 }
 
-/// Convert Payment to sequence.
-fn payment_to_seq(payment: &Payment) -> Vec<String> {
+/// Convert Payment to sequence. Errors of all invalid fields are added to `errors`.
+pub(crate) fn payment_to_seq(payment: &Payment, path: &str, errors: &mut Vec<BysqrError>) -> Vec<String> {
     let mut seq: Vec<String> = Vec::new();
 
     // PaymentOptions = order 1
-    if payment.payment_options == "paymentorder" {
-        seq.push(String::from("1"));
-    } else if payment.payment_options == "standingorder" {
-        seq.push(String::from("2"));
-    } else if payment.payment_options == "directdebit" {
-        seq.push(String::from("4"));
-    } else {
-        panic!("Encoding error: Unkown PaymentOptions value {}", payment.payment_options);
-    }
+    seq.push(format!("{}", payment.payment_options.bits()));
 
     // Amount = order 2
-    match payment.amount {
-        None => {
-            seq.push(String::new());
-        }
-        Some(value) => {
-            if ! value.is_sign_positive() {
-                panic!("Encoding error: The amount must be a positive number")
-            }
-
-            seq.push(as_decimal_str(value));
-        }
-    }
+    seq.push(check(as_amount_str(payment.amount, &format!("{}.Amount", path)), errors));
 
     // Currency = order 3
-    if Regex::new("[A-Z]{3}").unwrap().is_match(&payment.currency_code) {
-        seq.push(payment.currency_code.clone());
-    } else {
-        panic!("Encoding error: The currency code is not in ISO 4217 format");
-    }
+    seq.push(payment.currency_code.to_string());
 
     // Payment due date = order 4
-    if let Some(due_date) = &payment.payment_due_date {
-        seq.push(as_valid_date(due_date));
-    } else {
-        seq.push(String::new());
-    }
+    seq.push(as_date_str(&payment.payment_due_date));
 
     // Variable Symbol = order 5
-    seq.push(as_pattern_str(&payment.variable_symbol, r"^\d{0,10}$", "variable symbol"));
+    seq.push(as_optional_value(&payment.variable_symbol));
 
     // Constant Symbol = order 6
-    seq.push(as_pattern_str(&payment.constant_symbol, r"^\d{0,4}$", "constant symbol"));
+    seq.push(as_optional_value(&payment.constant_symbol));
 
     // Specific Symbol = order 7
-    seq.push(as_pattern_str(&payment.specific_symbol, r"^\d{0,10}$", "specific symbol"));
+    seq.push(as_optional_value(&payment.specific_symbol));
 
     // Originators Reference Information = order 8
-    seq.push(as_pattern_str(&payment.originators_reference_information, r"^.{0,35}$", "originators reference information"));
+    seq.push(check(as_pattern_str(&payment.originators_reference_information, r"^.{0,35}$", &format!("{}.OriginatorsReferenceInformation", path)), errors));
 
     // Payment Note = order 9
-    seq.push(as_pattern_str(&payment.payment_note, r"^[\p{L}\p{N}\p{P}\p{Z}\p{M}]{1,140}$", "payment note"));
+    seq.push(check(as_pattern_str(&payment.payment_note, r"^[\p{L}\p{N}\p{P}\p{Z}\p{M}]{1,140}$", &format!("{}.PaymentNote", path)), errors));
 
     // Bank Accounts = order 10
+    if payment.bank_accounts.bank_account.is_empty() {
+        errors.push(BysqrError::required_field(&format!("{}.BankAccounts.BankAccount", path)));
+    }
+
     seq.push(format!("{}", payment.bank_accounts.bank_account.len()));
     for bank_account in &payment.bank_accounts.bank_account {
         seq.append(&mut bank_account_to_seq(bank_account));
     }
 
-    // TODO: 11 StandingOrderExt
-    seq.push(String::from("0"));
+    // Standing Order Extension = order 11
+    if payment.payment_options.contains(PaymentOption::StandingOrder) {
+        let ext_path = format!("{}.StandingOrderExt", path);
 
-    // TODO: 12 DirectDebitExt
-    seq.push(String::from("0"));
+        if let Some(ext) = &payment.standing_order_ext {
+            seq.push(String::from("1"));
+            seq.append(&mut standing_order_ext_to_seq(ext, &ext_path, errors));
+        } else {
+            errors.push(BysqrError::required_field(&ext_path));
+        }
+    } else {
+        if payment.standing_order_ext.is_some() {
+            errors.push(BysqrError::invalid_field(&format!("{}.StandingOrderExt", path), "", Rule::PaymentOption(PaymentOption::StandingOrder.name())));
+        }
+
+        seq.push(String::from("0"));
+    }
+
+    // Direct Debit Extension = order 12
+    if payment.payment_options.contains(PaymentOption::DirectDebit) {
+        let ext_path = format!("{}.DirectDebitExt", path);
+
+        if let Some(ext) = &payment.direct_debit_ext {
+            seq.push(String::from("1"));
+            seq.append(&mut direct_debit_ext_to_seq(ext, &ext_path, errors));
+        } else {
+            errors.push(BysqrError::required_field(&ext_path));
+        }
+    } else {
+        if payment.direct_debit_ext.is_some() {
+            errors.push(BysqrError::invalid_field(&format!("{}.DirectDebitExt", path), "", Rule::PaymentOption(PaymentOption::DirectDebit.name())));
+        }
+
+        seq.push(String::from("0"));
+    }
 
     // Beneficiary Name = order 13
-    seq.push(as_pattern_str(&payment.beneficiary_name, r"^.{0,140}$", "beneficiary name"));
+    seq.push(check(as_pattern_str(&payment.beneficiary_name, r"^.{0,140}$", &format!("{}.BeneficiaryName", path)), errors));
 
     // Beneficiary Address Line 1 = order 14
-    seq.push(as_pattern_str(&payment.beneficiary_address_line_1, r"^.{0,70}$", "beneficiary address line 1"));
+    seq.push(check(as_pattern_str(&payment.beneficiary_address_line_1, r"^.{0,70}$", &format!("{}.BeneficiaryAddressLine1", path)), errors));
 
     // Beneficiary Address Line 2 = order 15
-    seq.push(as_pattern_str(&payment.beneficiary_address_line_2, r"^.{0,70}$", "beneficiary address line 2"));
+    seq.push(check(as_pattern_str(&payment.beneficiary_address_line_2, r"^.{0,70}$", &format!("{}.BeneficiaryAddressLine2", path)), errors));
+
+    seq
+}
+
+fn as_required_str(value: &str, pattern: &'static str, path: &str) -> Result<String, BysqrError> {
+    if value.is_empty() {
+        Err(BysqrError::required_field(path))
+    } else {
+        as_pattern_str(&Some(value.to_string()), pattern, path)
+    }
+}
+
+/// Numbers are limited to 15 characters by the XSD, including the sign and the decimal point.
+fn as_number_str<T: ToString>(value: &T, path: &str) -> Result<String, BysqrError> {
+    as_pattern_str(&Some(value.to_string()), r"^.{1,15}$", path)
+}
+
+fn as_decimal_str(value: &Option<Decimal>, path: &str) -> Result<String, BysqrError> {
+    match value {
+        Some(decimal) => as_number_str(decimal, path),
+        None => Ok(String::new()),
+    }
+}
+
+/// Percentage is written in the normalized form, e.g. "0.2" for 20%.
+fn as_percentage_str(value: &Decimal, path: &str) -> Result<String, BysqrError> {
+    let percentage = value.to_string();
+
+    if percentage == "1" || percentage == "0" || percentage.starts_with("0.") {
+        Ok(percentage)
+    } else {
+        Err(BysqrError::invalid_field(path, &percentage, Rule::Range(0, 1)))
+    }
+}
+
+fn party_to_seq(
+    name: &str,
+    tax_id: &Option<String>,
+    vat_id: &Option<String>,
+    register_id: &Option<String>,
+    path: &str,
+    errors: &mut Vec<BysqrError>,
+) -> Vec<String> {
+    let mut seq: Vec<String> = Vec::new();
+
+    // Party Name = order 1
+    seq.push(check(as_required_str(name, r"^.{1,20}$", &format!("{}.PartyName", path)), errors));
+
+    // Company Tax ID = order 2
+    seq.push(check(as_pattern_str(tax_id, r"^.{0,12}$", &format!("{}.CompanyTaxID", path)), errors));
+
+    // Company VAT ID = order 3
+    seq.push(check(as_pattern_str(vat_id, r"^.{0,14}$", &format!("{}.CompanyVATID", path)), errors));
+
+    // Company Register ID = order 4
+    seq.push(check(as_pattern_str(register_id, r"^.{0,14}$", &format!("{}.CompanyRegisterID", path)), errors));
+
+    seq
+}
+
+fn postal_address_to_seq(address: &PostalAddress, path: &str, errors: &mut Vec<BysqrError>) -> Vec<String> {
+    let mut seq: Vec<String> = Vec::new();
+
+    // Street Name = order 1
+    seq.push(check(as_required_str(&address.street_name, r"^.{1,20}$", &format!("{}.StreetName", path)), errors));
+
+    // Building Number = order 2
+    seq.push(check(as_pattern_str(&address.building_number, r"^.{0,3}$", &format!("{}.BuildingNumber", path)), errors));
+
+    // City Name = order 3
+    seq.push(check(as_required_str(&address.city_name, r"^.{1,20}$", &format!("{}.CityName", path)), errors));
+
+    // Postal Zone = order 4
+    seq.push(check(as_required_str(&address.postal_zone, r"^.{1,10}$", &format!("{}.PostalZone", path)), errors));
+
+    // State = order 5
+    seq.push(check(as_pattern_str(&address.state, r"^.{0,10}$", &format!("{}.State", path)), errors));
+
+    // Country = order 6
+    seq.push(address.country.to_string());
+
+    seq
+}
+
+fn supplier_party_to_seq(party: &SupplierParty, path: &str, errors: &mut Vec<BysqrError>) -> Vec<String> {
+    let mut seq = party_to_seq(
+        &party.party_name,
+        &party.company_tax_id,
+        &party.company_vat_id,
+        &party.company_register_id,
+        path,
+        errors,
+    );
+
+    // Postal Address = order 5
+    seq.append(&mut postal_address_to_seq(&party.postal_address, &format!("{}.PostalAddress", path), errors));
+
+    // Contact = order 6, all fields are empty when missing
+    match &party.contact {
+        Some(contact) => {
+            seq.push(check(as_pattern_str(&contact.name, r"^.{0,20}$", &format!("{}.Contact.Name", path)), errors));
+            seq.push(check(as_pattern_str(&contact.telephone, r"^.{0,12}$", &format!("{}.Contact.Telephone", path)), errors));
+            seq.push(check(as_pattern_str(&contact.email, r"^.{0,40}$", &format!("{}.Contact.EMail", path)), errors));
+        }
+        None => seq.append(&mut vec![String::new(); 3]),
+    }
+
+    seq
+}
+
+fn customer_party_to_seq(party: &CustomerParty, path: &str, errors: &mut Vec<BysqrError>) -> Vec<String> {
+    let mut seq = party_to_seq(
+        &party.party_name,
+        &party.company_tax_id,
+        &party.company_vat_id,
+        &party.company_register_id,
+        path,
+        errors,
+    );
+
+    // Party Identification = order 5
+    seq.push(check(as_pattern_str(&party.party_identification, r"^.{0,20}$", &format!("{}.PartyIdentification", path)), errors));
+
+    seq
+}
+
+/// Item Name = order 3 or Item EAN Code = order 4, Period From Date = order 5 and Period To Date = order 6,
+/// shared by the single invoice line and lines of invoice items.
+fn item_to_seq(
+    item_name: &Option<String>,
+    item_ean_code: &Option<String>,
+    period_from_date: &Option<NaiveDate>,
+    period_to_date: &Option<NaiveDate>,
+    path: &str,
+    errors: &mut Vec<BysqrError>,
+) -> Vec<String> {
+    let mut seq: Vec<String> = Vec::new();
+
+    match (item_name, item_ean_code) {
+        (None, None) => errors.push(BysqrError::required_field(&format!("{}.ItemName", path))),
+        (Some(_), Some(ean_code)) => errors.push(BysqrError::invalid_field(&format!("{}.ItemEANCode", path), ean_code, Rule::Exclusive("ItemName"))),
+        _ => {}
+    }
+
+    seq.push(check(as_pattern_str(item_name, r"^.{0,30}$", &format!("{}.ItemName", path)), errors));
+    seq.push(check(as_pattern_str(item_ean_code, r"^.{0,30}$", &format!("{}.ItemEANCode", path)), errors));
+
+    // Period is given with both dates or none
+    match (period_from_date, period_to_date) {
+        (Some(_), None) => errors.push(BysqrError::required_field(&format!("{}.PeriodToDate", path))),
+        (None, Some(_)) => errors.push(BysqrError::required_field(&format!("{}.PeriodFromDate", path))),
+        _ => {}
+    }
+
+    seq.push(as_date_str(period_from_date));
+    seq.push(as_date_str(period_to_date));
+
+    seq
+}
+
+fn single_invoice_line_to_seq(line: &SingleInvoiceLine, path: &str, errors: &mut Vec<BysqrError>) -> Vec<String> {
+    let mut seq: Vec<String> = Vec::new();
+
+    // Order Line ID = order 1
+    seq.push(check(as_pattern_str(&line.order_line_id, r"^.{0,10}$", &format!("{}.OrderLineID", path)), errors));
+
+    // Delivery Note Line ID = order 2
+    seq.push(check(as_pattern_str(&line.delivery_note_line_id, r"^.{0,10}$", &format!("{}.DeliveryNoteLineID", path)), errors));
+
+    // Item = order 3 - 6
+    seq.append(&mut item_to_seq(
+        &line.item_name,
+        &line.item_ean_code,
+        &line.period_from_date,
+        &line.period_to_date,
+        path,
+        errors,
+    ));
+
+    // Invoiced Quantity = order 7
+    seq.push(check(as_number_str(&line.invoiced_quantity, &format!("{}.InvoicedQuantity", path)), errors));
+
+    seq
+}
+
+fn tax_category_summary_to_seq(summary: &TaxCategorySummary, path: &str, errors: &mut Vec<BysqrError>) -> Vec<String> {
+    vec![
+        // Classified Tax Category = order 1
+        check(as_percentage_str(&summary.classified_tax_category, &format!("{}.ClassifiedTaxCategory", path)), errors),
+        // Tax Exclusive Amount = order 2
+        check(as_number_str(&summary.tax_exclusive_amount, &format!("{}.TaxExclusiveAmount", path)), errors),
+        // Tax Amount = order 3
+        check(as_number_str(&summary.tax_amount, &format!("{}.TaxAmount", path)), errors),
+        // Already Claimed Tax Exclusive Amount = order 4
+        check(as_number_str(&summary.already_claimed_tax_exclusive_amount, &format!("{}.AlreadyClaimedTaxExclusiveAmount", path)), errors),
+        // Already Claimed Tax Amount = order 5
+        check(as_number_str(&summary.already_claimed_tax_amount, &format!("{}.AlreadyClaimedTaxAmount", path)), errors),
+    ]
+}
+
+/// Convert Invoice to sequence, checking every field. Errors of all invalid fields are added to `errors`.
+pub(crate) fn invoice_to_seq(invoice: &Invoice, errors: &mut Vec<BysqrError>) -> Vec<String> {
+    // Rates are given only together with the foreign currency
+    if invoice.foreign_currency_code.is_some() {
+        if invoice.curr_rate.is_none() {
+            errors.push(BysqrError::required_field("CurrRate"));
+        }
+
+        if invoice.reference_curr_rate.is_none() {
+            errors.push(BysqrError::required_field("ReferenceCurrRate"));
+        }
+    } else if invoice.curr_rate.is_some() || invoice.reference_curr_rate.is_some() {
+        errors.push(BysqrError::required_field("ForeignCurrencyCode"));
+    }
+
+    let mut seq: Vec<String> = vec![
+        // InvoiceID = order 1
+        check(as_required_str(&invoice.invoice_id, r"^.{1,10}$", "InvoiceID"), errors),
+        // Issue Date = order 2
+        invoice.issue_date.format("%Y%m%d").to_string(),
+        // Tax Point Date = order 3
+        as_date_str(&invoice.tax_point_date),
+        // Order ID = order 4
+        check(as_pattern_str(&invoice.order_id, r"^.{0,10}$", "OrderID"), errors),
+        // Delivery Note ID = order 5
+        check(as_pattern_str(&invoice.delivery_note_id, r"^.{0,10}$", "DeliveryNoteID"), errors),
+        // Local Currency Code = order 6
+        invoice.local_currency_code.to_string(),
+        // Foreign Currency Code = order 7
+        as_optional_value(&invoice.foreign_currency_code),
+        // Curr Rate = order 8
+        check(as_decimal_str(&invoice.curr_rate, "CurrRate"), errors),
+        // Reference Curr Rate = order 9
+        check(as_decimal_str(&invoice.reference_curr_rate, "ReferenceCurrRate"), errors),
+    ];
+
+    // Supplier Party = order 10
+    seq.append(&mut supplier_party_to_seq(&invoice.supplier_party, "SupplierParty", errors));
+
+    // Customer Party = order 11
+    seq.append(&mut customer_party_to_seq(&invoice.customer_party, "CustomerParty", errors));
+
+    // Number Of Invoice Lines = order 12 and Invoice Description = order 13, or Single Invoice Line = order 14
+    match (&invoice.number_of_invoice_lines, &invoice.single_invoice_line) {
+        (None, None) => errors.push(BysqrError::required_field("NumberOfInvoiceLines")),
+        (Some(lines), Some(_)) => errors.push(BysqrError::invalid_field("NumberOfInvoiceLines", &lines.to_string(), Rule::Exclusive("SingleInvoiceLine"))),
+        _ => {}
+    }
+
+    seq.push(invoice.number_of_invoice_lines.map(|lines| lines.to_string()).unwrap_or_default());
+
+    if let (Some(description), Some(_)) = (&invoice.invoice_description, &invoice.single_invoice_line) {
+        errors.push(BysqrError::invalid_field("InvoiceDescription", description, Rule::Exclusive("SingleInvoiceLine")));
+    }
+
+    seq.push(check(as_pattern_str(&invoice.invoice_description, r"^.{0,30}$", "InvoiceDescription"), errors));
+
+    match &invoice.single_invoice_line {
+        Some(line) => seq.append(&mut single_invoice_line_to_seq(line, "SingleInvoiceLine", errors)),
+        None => seq.append(&mut vec![String::new(); 7]),
+    }
+
+    // Tax Category Summaries = order 15
+    let summaries = &invoice.tax_category_summaries.tax_category_summary;
+
+    if summaries.is_empty() {
+        errors.push(BysqrError::required_field("TaxCategorySummaries.TaxCategorySummary"));
+    }
+
+    seq.push(format!("{}", summaries.len()));
+    for (index, summary) in summaries.iter().enumerate() {
+        seq.append(&mut tax_category_summary_to_seq(summary, &format!("TaxCategorySummaries.TaxCategorySummary[{}]", index), errors));
+    }
+
+    // Monetary Summary = order 16, computed amounts are not part of the sequence
+    seq.push(check(as_number_str(&invoice.monetary_summary.payable_rounding_amount, "MonetarySummary.PayableRoundingAmount"), errors));
+    seq.push(check(as_amount_str(Some(invoice.monetary_summary.paid_deposits_amount), "MonetarySummary.PaidDepositsAmount"), errors));
+
+    // Payment Means = order 17
+    seq.push(invoice.payment_means.map(|means| format!("{}", means.bits())).unwrap_or_default());
+
+    seq
+}
+
+fn invoice_line_to_seq(line: &InvoiceLine, path: &str, errors: &mut Vec<BysqrError>) -> Vec<String> {
+    let mut seq: Vec<String> = Vec::new();
+
+    // Order Reference = order 1, both fields are empty when missing
+    match &line.order_reference {
+        Some(reference) => {
+            seq.push(check(as_pattern_str(&reference.order_id, r"^.{0,10}$", &format!("{}.OrderReference.OrderID", path)), errors));
+            seq.push(check(as_pattern_str(&reference.order_line_id, r"^.{0,10}$", &format!("{}.OrderReference.OrderLineID", path)), errors));
+        }
+        None => seq.append(&mut vec![String::new(); 2]),
+    }
+
+    // Delivery Note Reference = order 2, both fields are empty when missing
+    match &line.delivery_note_reference {
+        Some(reference) => {
+            seq.push(check(as_pattern_str(&reference.delivery_note_id, r"^.{0,10}$", &format!("{}.DeliveryNoteReference.DeliveryNoteID", path)), errors));
+            seq.push(check(as_pattern_str(&reference.delivery_note_line_id, r"^.{0,10}$", &format!("{}.DeliveryNoteReference.DeliveryNoteLineID", path)), errors));
+        }
+        None => seq.append(&mut vec![String::new(); 2]),
+    }
+
+    // Item = order 3 - 6
+    seq.append(&mut item_to_seq(
+        &line.item_name,
+        &line.item_ean_code,
+        &line.period_from_date,
+        &line.period_to_date,
+        path,
+        errors,
+    ));
+
+    // Invoiced Quantity = order 7
+    seq.push(check(as_number_str(&line.invoiced_quantity, &format!("{}.InvoicedQuantity", path)), errors));
+
+    // Unit Price Tax Exclusive Amount = order 8
+    seq.push(check(as_number_str(&line.unit_price_tax_exclusive_amount, &format!("{}.UnitPriceTaxExclusiveAmount", path)), errors));
+
+    // Unit Price Tax Amount = order 9
+    seq.push(check(as_number_str(&line.unit_price_tax_amount, &format!("{}.UnitPriceTaxAmount", path)), errors));
+
+    // Classified Tax Category = order 10
+    seq.push(check(as_percentage_str(&line.classified_tax_category, &format!("{}.ClassifiedTaxCategory", path)), errors));
+
+    seq
+}
+
+/// Convert InvoiceItems to sequence, checking every field. Errors of all invalid fields are added to `errors`.
+pub(crate) fn invoice_items_to_seq(items: &InvoiceItems, errors: &mut Vec<BysqrError>) -> Vec<String> {
+    let mut seq: Vec<String> = vec![
+        // InvoiceID = order 1
+        check(as_required_str(&items.invoice_id, r"^.{1,10}$", "InvoiceID"), errors),
+        // First Invoice Line ID = order 2
+        check(as_required_str(&items.first_invoice_line_id, r"^.{1,10}$", "FirstInvoiceLineID"), errors),
+    ];
+
+    // Invoice Lines = order 3
+    let lines = &items.invoice_lines.invoice_line;
+
+    if lines.is_empty() {
+        errors.push(BysqrError::required_field("InvoiceLines.InvoiceLine"));
+    }
+
+    seq.push(format!("{}", lines.len()));
+    for (index, line) in lines.iter().enumerate() {
+        seq.append(&mut invoice_line_to_seq(line, &format!("InvoiceLines.InvoiceLine[{}]", index), errors));
+    }
 
     seq
 }
@@ -173,19 +633,33 @@ fn base32_encode(bytes: &[u8]) -> String {
     result
 }
 
-// TODO: ak obsahuje hodnota \t tak musi to byť vymenene za space char
-pub fn encode(pay: &Pay) -> String {
+/// Convert Pay to sequence, checking every field. Errors of all invalid fields are added to `errors`,
+/// the sequence is usable only when there are none.
+pub(crate) fn pay_to_seq(pay: &Pay, errors: &mut Vec<BysqrError>) -> Vec<String> {
     let mut buf: Vec<String> = Vec::new();
 
+    // InvoiceID = order 1
+    buf.push(check(as_pattern_str(&pay.invoice_id, r"^.{0,10}$", "InvoiceID"), errors));
+
+    // Payments = order 2
+    if pay.payments.payment.is_empty() {
+        errors.push(BysqrError::required_field("Payments.Payment"));
+    }
+
     buf.push(format!("{}", pay.payments.payment.len()));
 
-    for payment in &pay.payments.payment {
-        let mut encoded = payment_to_seq(&payment);
+    for (index, payment) in pay.payments.payment.iter().enumerate() {
+        let mut encoded = payment_to_seq(payment, &format!("Payments.Payment[{}]", index), errors);
 
         buf.append(&mut encoded);
     }
 
-    let seq = format!("\t{}", buf.join("\t"));
+    buf
+}
+
+/// Compress the sequence and encode it with the header of the by square type and document type.
+fn encode_seq(header: Header, buf: &[String]) -> Result<String, BysqrError> {
+    let seq = buf.join("\t");
 
     let mut hasher = Hasher::new();
     hasher.update(seq.as_bytes());
@@ -194,33 +668,21 @@ pub fn encode(pay: &Pay) -> String {
     let mut to_compress: Vec<u8> = Vec::from(crc.to_le_bytes());
     to_compress.extend_from_slice(seq.as_bytes());
 
-    let mut options = LzmaOptions::new_preset(6).unwrap();
-    options.literal_context_bits(3);
-    options.literal_position_bits(0);
-    options.position_bits(2);
-    options.dict_size(131072);
-
-    let stream = Stream::new_lzma_encoder(&options).unwrap();
-
-    let mut compressor = XzEncoder::new_stream(Vec::new(), stream);
-    compressor.write_all(&to_compress).unwrap();
+    let compressed = compress(&to_compress).map_err(|e| BysqrError::Compression(e.to_string()))?;
 
-    let compressed = &compressor.finish().unwrap()[13..];
-
-    let square_type: u16 = 0;
-    let version: u16 = 0;
-    let document_type: u16 = 0;
-    let reserved: u16 = 0;
-
-    // TODO: Check či maju byt le alebo be tieto bity
-    let header = ((square_type & 0b1111) << 12 | (version & 0b1111) << 8 | (document_type & 0b1111) << 4 | reserved & 0b1111).to_le_bytes();
+    // Length of the sequence with checksum before compression, the decoder needs it to restore the LZMA header
+    let size = u16::try_from(to_compress.len()).map_err(|_| BysqrError::PayloadTooLarge(to_compress.len()))?;
 
     let mut payload: Vec<u8> = Vec::new();
-    payload.extend_from_slice(&header);
-    // TODO: Velkost paylodu pred kompresiou je potrebna?
-    payload.extend_from_slice(&((to_compress.len() & 0b11111111) as u16).to_le_bytes());
+    payload.extend_from_slice(&header.to_bytes());
+    payload.extend_from_slice(&size.to_le_bytes());
     payload.extend_from_slice(&compressed);
 
+    Ok(payload_to_base32(&payload))
+}
+
+/// Split the payload into 5-bit groups, padded with zero bits, and encode them with base32hex.
+pub(crate) fn payload_to_base32(payload: &[u8]) -> String {
     let mut payload_bin: String = payload
         .iter()
         .map(|byte| format!("{:08b}", byte))
@@ -229,7 +691,7 @@ pub fn encode(pay: &Pay) -> String {
 
     let trailing = payload_bin.len() % 5;
     if trailing > 0 {
-        payload_bin.push_str(&std::iter::repeat('0').take(5 - trailing).collect::<String>());
+        payload_bin.push_str(&"0".repeat(5 - trailing));
     }
 
     let base_5: Vec<u8> = payload_bin
@@ -245,3 +707,105 @@ pub fn encode(pay: &Pay) -> String {
 
     base32_encode(&base_5)
 }
+
+/// Encode the Pay. Control characters in values of fields are replaced by spaces.
+pub fn encode(pay: &Pay) -> Result<String, BysqrError> {
+    encode_pay(pay, Version::default(), ControlCharacters::default())
+}
+
+fn encode_pay(pay: &Pay, version: Version, control_characters: ControlCharacters) -> Result<String, BysqrError> {
+    let mut errors: Vec<BysqrError> = Vec::new();
+    let mut pay = pay.clone();
+
+    sanitize_pay(&mut pay, control_characters, &mut errors);
+    let buf = pay_to_seq(&pay, &mut errors);
+
+    // Report the first invalid field, use validate to get all of them
+    if let Some(error) = errors.into_iter().next() {
+        return Err(error);
+    }
+
+    encode_seq(Header::new(0, 0).with_version(version), &buf)
+}
+
+/// Encode the Invoice, the document type is taken from the type of the invoice.
+/// Control characters in values of fields are replaced by spaces.
+pub fn encode_invoice(invoice: &Invoice) -> Result<String, BysqrError> {
+    encode_invoice_document(invoice, Version::default(), ControlCharacters::default())
+}
+
+fn encode_invoice_document(invoice: &Invoice, version: Version, control_characters: ControlCharacters) -> Result<String, BysqrError> {
+    let mut errors: Vec<BysqrError> = Vec::new();
+    let mut invoice = invoice.clone();
+
+    sanitize_invoice(&mut invoice, control_characters, &mut errors);
+    let buf = invoice_to_seq(&invoice, &mut errors);
+
+    if let Some(error) = errors.into_iter().next() {
+        return Err(error);
+    }
+
+    encode_seq(Header::new(1, invoice.document_type.code()).with_version(version), &buf)
+}
+
+/// Encode the list of invoice items. Control characters in values of fields are replaced by spaces.
+pub fn encode_invoice_items(items: &InvoiceItems) -> Result<String, BysqrError> {
+    encode_invoice_items_document(items, Version::default(), ControlCharacters::default())
+}
+
+fn encode_invoice_items_document(items: &InvoiceItems, version: Version, control_characters: ControlCharacters) -> Result<String, BysqrError> {
+    let mut errors: Vec<BysqrError> = Vec::new();
+    let mut items = items.clone();
+
+    sanitize_invoice_items(&mut items, control_characters, &mut errors);
+    let buf = invoice_items_to_seq(&items, &mut errors);
+
+    if let Some(error) = errors.into_iter().next() {
+        return Err(error);
+    }
+
+    encode_seq(Header::new(2, 0).with_version(version), &buf)
+}
+
+/// Encode the Pay with options. Returns encoded content and report of truncated fields,
+/// which is empty if the truncation was not requested or not needed.
+pub fn encode_with_options(pay: &Pay, options: &EncodeOptions) -> Result<(String, TruncationReport), BysqrError> {
+    let encode = |pay: &Pay| encode_pay(pay, options.version, options.control_characters);
+
+    match options.truncate {
+        None => Ok((encode(pay)?, TruncationReport::default())),
+        Some(limit) => {
+            let (truncated, report) = truncate(pay, limit, encode)?;
+
+            Ok((encode(&truncated)?, report))
+        }
+    }
+}
+
+/// Encode the Invoice with options, the same way as encode_with_options encodes the Pay.
+pub fn encode_invoice_with_options(invoice: &Invoice, options: &EncodeOptions) -> Result<(String, TruncationReport), BysqrError> {
+    let encode = |invoice: &Invoice| encode_invoice_document(invoice, options.version, options.control_characters);
+
+    match options.truncate {
+        None => Ok((encode(invoice)?, TruncationReport::default())),
+        Some(limit) => {
+            let (truncated, report) = truncate_invoice(invoice, limit, encode)?;
+
+            Ok((encode(&truncated)?, report))
+        }
+    }
+}
+
+/// Encode the list of invoice items with options, the same way as encode_with_options encodes the Pay.
+pub fn encode_invoice_items_with_options(items: &InvoiceItems, options: &EncodeOptions) -> Result<(String, TruncationReport), BysqrError> {
+    let encode = |items: &InvoiceItems| encode_invoice_items_document(items, options.version, options.control_characters);
+
+    match options.truncate {
+        None => Ok((encode(items)?, TruncationReport::default())),
+        Some(limit) => {
+            let (truncated, report) = truncate_invoice_items(items, limit, encode)?;
+
+            Ok((encode(&truncated)?, report))
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use std::fmt;
+
+use crate::decoder::DecodeError;
+
+/// Rule of the specification which a field value has to satisfy.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rule {
+    /// The value must match the regular expression.
+    Pattern(&'static str),
+    /// The value must be a valid date in "YYYY-MM-DD" or "YYYYMMDD" format.
+    Date,
+    /// The value must be a positive number.
+    Positive,
+    /// The number must be within the inclusive range.
+    Range(u32, u32),
+    /// The value must be one of the listed values.
+    OneOf(Vec<&'static str>),
+    /// The value must have exactly this number of characters.
+    Length(usize),
+    /// The check digits of the value must be valid.
+    Checksum,
+    /// The field must not be present together with the named field.
+    Exclusive(&'static str),
+    /// The value must not contain tabs, line breaks or other control characters.
+    ControlCharacter,
+    /// The field may be present only when PaymentOptions contains the named option.
+    PaymentOption(&'static str),
+    /// The field is required.
+    Required,
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rule::Pattern(pattern) => write!(f, "must match pattern {}", pattern),
+            Rule::Date => write!(f, "must be a valid date in YYYY-MM-DD format"),
+            Rule::Positive => write!(f, "must be a positive number"),
+            Rule::Range(min, max) => write!(f, "must be a number between {} and {}", min, max),
+            Rule::OneOf(values) => write!(f, "must be one of {}", values.join(", ")),
+            Rule::Length(length) => write!(f, "must be {} characters long", length),
+            Rule::Checksum => write!(f, "must have valid check digits"),
+            Rule::Exclusive(other) => write!(f, "must not be combined with {}", other),
+            Rule::ControlCharacter => write!(f, "must not contain control characters"),
+            Rule::PaymentOption(option) => write!(f, "requires PaymentOptions to contain {}", option),
+            Rule::Required => write!(f, "is required"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum BysqrError {
+    /// A field of the document does not satisfy a rule of the specification.
+    /// Path of the field follows the XML structure, e.g. "Payments.Payment[0].BankAccounts.BankAccount[1].IBAN".
+    InvalidField { path: String, value: String, rule: Rule },
+    /// The source could not be deserialized as XML or JSON document.
+    Deserialize(String),
+    /// The document could not be serialized to XML or JSON.
+    Serialize(String),
+    /// The XML document does not match the structure of the specification, in strict mode.
+    Schema { line: u64, column: u64, message: String },
+    /// The source is neither XML nor JSON document.
+    UnknownFormat,
+    /// The encoded content does not fit into the requested limit, even after truncation.
+    TruncationLimit,
+    /// LZMA compression of the payload failed.
+    Compression(String),
+    /// The sequence with its checksum is longer than the 16-bit length field of the payload allows.
+    PayloadTooLarge(usize),
+    /// The content could not be decoded.
+    Decode(DecodeError),
+    /// The QR code could not be created from the content.
+    Qr(String),
+    /// The SVG image could not be created or rendered.
+    Render(String),
+    /// No readable QR code was found in the image.
+    Scan(String),
+    /// The file name could not be created from the template.
+    Template(String),
+    /// The preview window could not be opened.
+    Preview(String),
+}
+
+impl BysqrError {
+    pub fn invalid_field(path: &str, value: &str, rule: Rule) -> Self {
+        BysqrError::InvalidField { path: path.to_string(), value: value.to_string(), rule }
+    }
+
+    pub fn required_field(path: &str) -> Self {
+        BysqrError::InvalidField { path: path.to_string(), value: String::new(), rule: Rule::Required }
+    }
+}
+
+impl fmt::Display for BysqrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BysqrError::InvalidField { path, rule: rule @ (Rule::Required | Rule::PaymentOption(_)), .. } => write!(f, "The {} {}", path, rule),
+            BysqrError::InvalidField { path, value, rule } => write!(f, "The {} value \"{}\" {}", path, value, rule),
+            BysqrError::Deserialize(reason) => write!(f, "unable to deserialize source: {}", reason),
+            BysqrError::Serialize(reason) => write!(f, "unable to serialize document: {}", reason),
+            BysqrError::Schema { line, column, message } => write!(f, "invalid XML document at line {}, column {}: {}", line, column, message),
+            BysqrError::UnknownFormat => write!(f, "unable to deserialize source: expected XML or JSON document"),
+            BysqrError::TruncationLimit => write!(f, "Encoding error: The payment does not fit into the requested limit even after truncation"),
+            BysqrError::Compression(reason) => write!(f, "Encoding error: Unable to compress payload: {}", reason),
+            BysqrError::PayloadTooLarge(size) => write!(f, "Encoding error: The payload of {} bytes exceeds maximum of {} bytes", size, u16::MAX),
+            BysqrError::Decode(error) => write!(f, "{}", error),
+            BysqrError::Qr(reason) => write!(f, "unable to create QR code: {}", reason),
+            BysqrError::Render(reason) => write!(f, "unable to render image: {}", reason),
+            BysqrError::Scan(reason) => write!(f, "unable to read QR code: {}", reason),
+            BysqrError::Template(reason) => write!(f, "unable to create file name: {}", reason),
+            BysqrError::Preview(reason) => write!(f, "unable to show preview: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for BysqrError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BysqrError::Decode(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<DecodeError> for BysqrError {
+    fn from(error: DecodeError) -> Self {
+        BysqrError::Decode(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BysqrError, Rule};
+
+    #[test]
+    fn formats_invalid_field_the_same_for_every_source() {
+        let error = BysqrError::invalid_field("Payments.Payment[0].CurrencyCode", "eur", Rule::Pattern("^[A-Z]{3}$"));
+        assert_eq!(error.to_string(), "The Payments.Payment[0].CurrencyCode value \"eur\" must match pattern ^[A-Z]{3}$");
+
+        let error = BysqrError::required_field("Payments.Payment[0].BankAccounts");
+        assert_eq!(error.to_string(), "The Payments.Payment[0].BankAccounts is required");
+    }
+}
@@ -0,0 +1,94 @@
+//! Values of fields which do not satisfy their type, such as IBAN, symbols, dates or code lists, reported
+//! with path of the field. Types validated on deserialization parse their value with `parse_value`, which
+//! lets `collect` find every invalid value of the document instead of stopping at the first one.
+
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::error::{BysqrError, Rule};
+
+/// Parse the value of the field, or record the error with path of the field and continue without the value.
+pub(crate) fn parse_field<T: FromStr<Err = Rule>>(value: &str, path: &str, errors: &mut Vec<BysqrError>) -> Option<T> {
+    match value.parse() {
+        Ok(value) => Some(value),
+        Err(rule) => {
+            errors.push(BysqrError::invalid_field(path, value, rule));
+            None
+        }
+    }
+}
+
+/// True when the field on `path` is the field on `ancestor` or one of its descendants.
+pub(crate) fn is_within(path: &str, ancestor: &str) -> bool {
+    match path.strip_prefix(ancestor) {
+        Some(rest) => rest.is_empty() || rest.starts_with('.') || rest.starts_with('['),
+        None => false,
+    }
+}
+
+/// Invalid values found by `collect` in the current pass over the document.
+struct Collector {
+    /// Number of invalid values found in the previous passes, which are replaced by placeholders.
+    tolerated: usize,
+    seen: usize,
+    rejected: Option<(String, Rule)>,
+}
+
+thread_local! {
+    static COLLECTOR: RefCell<Option<Collector>> = const { RefCell::new(None) };
+}
+
+/// Parse the value of a type validated on deserialization. Within `collect`, invalid values found in the previous
+/// passes are replaced by `placeholder`, so the deserialization fails on the next one.
+pub(crate) fn parse_value<T, E: serde::de::Error>(
+    value: &str,
+    parse: impl FnOnce(&str) -> Result<T, Rule>,
+    placeholder: impl FnOnce() -> T,
+) -> Result<T, E> {
+    let rule = match parse(value) {
+        Ok(parsed) => return Ok(parsed),
+        Err(rule) => rule,
+    };
+
+    let message = format!("value \"{}\" {}", value, rule);
+
+    let tolerated = COLLECTOR.with_borrow_mut(|collector| match collector {
+        Some(collector) if collector.seen < collector.tolerated => {
+            collector.seen += 1;
+            true
+        }
+        Some(collector) => {
+            collector.rejected = Some((value.to_string(), rule));
+            false
+        }
+        None => false,
+    });
+
+    if tolerated {
+        Ok(placeholder())
+    } else {
+        Err(E::custom(message))
+    }
+}
+
+/// Deserialize the document, collecting every value which does not satisfy its type with path of the field.
+/// The document is read again after each invalid value, until it is read with all of them replaced by placeholders,
+/// so the document is usable only when there are no errors. Fails when the document itself can not be read.
+pub(crate) fn collect<T, E: Display>(
+    deserialize: impl Fn() -> Result<T, serde_path_to_error::Error<E>>,
+) -> Result<(T, Vec<BysqrError>), BysqrError> {
+    let mut errors: Vec<BysqrError> = Vec::new();
+
+    loop {
+        COLLECTOR.set(Some(Collector { tolerated: errors.len(), seen: 0, rejected: None }));
+        let result = deserialize();
+        let rejected = COLLECTOR.take().and_then(|collector| collector.rejected);
+
+        match (result, rejected) {
+            (Ok(document), _) => return Ok((document, errors)),
+            (Err(error), Some((value, rule))) => errors.push(BysqrError::invalid_field(&error.path().to_string(), &value, rule)),
+            (Err(error), None) => return Err(BysqrError::Deserialize(error.to_string())),
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::decoder::DecodeError;
+
+/// Version of the by square specification, written into the header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Version {
+    #[default]
+    V1_0_0,
+    V1_1_0,
+    V1_2_0,
+}
+
+impl Version {
+    const NAMES: [(Version, &'static str); 3] = [
+        (Version::V1_0_0, "1.0.0"),
+        (Version::V1_1_0, "1.1.0"),
+        (Version::V1_2_0, "1.2.0"),
+    ];
+
+    /// Version written into the header, 0 for 1.0.0, 1 for 1.1.0 and 2 for 1.2.0.
+    pub fn code(&self) -> u8 {
+        *self as u8
+    }
+
+    pub fn from_code(code: u8) -> Option<Version> {
+        Version::NAMES.get(code as usize).map(|(version, _)| *version)
+    }
+}
+
+impl FromStr for Version {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Version::NAMES
+            .iter()
+            .find(|(_, name)| *name == value)
+            .map(|(version, _)| *version)
+            .ok_or_else(|| format!("unknown version {}, expected one of 1.0.0, 1.1.0 or 1.2.0", value))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Version::NAMES[self.code() as usize].1)
+    }
+}
+
+/// Header of the by square content, 4 nibbles in order bysquareType, version, documentType and reserved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    /// 0 for PAY by square, 1 for INVOICE by square and 2 for the list of invoice items.
+    pub square_type: u8,
+    pub version: Version,
+    /// Type of the document within the by square type, e.g. the invoice document type.
+    pub document_type: u8,
+    /// Reserved for future use, always 0.
+    pub reserved: u8,
+}
+
+impl Header {
+    pub fn new(square_type: u8, document_type: u8) -> Self {
+        Header { square_type, version: Version::default(), document_type, reserved: 0 }
+    }
+
+    pub fn with_version(self, version: Version) -> Self {
+        Header { version, ..self }
+    }
+
+    pub fn to_bytes(&self) -> [u8; 2] {
+        [
+            (self.square_type & 0b1111) << 4 | self.version.code() & 0b1111,
+            (self.document_type & 0b1111) << 4 | self.reserved & 0b1111,
+        ]
+    }
+
+    /// Read the header from the first two bytes of the payload. Versions unknown to this
+    /// implementation are not supported, since their content may be structured differently.
+    pub fn from_bytes(bytes: [u8; 2]) -> Result<Self, DecodeError> {
+        let square_type = bytes[0] >> 4;
+        let document_type = bytes[1] >> 4;
+
+        match Version::from_code(bytes[0] & 0b1111) {
+            Some(version) => Ok(Header { square_type, version, document_type, reserved: bytes[1] & 0b1111 }),
+            None => Err(DecodeError::UnsupportedDocument { square_type, version: bytes[0] & 0b1111, document_type }),
+        }
+    }
+
+    pub(crate) fn unsupported(&self) -> DecodeError {
+        DecodeError::UnsupportedDocument {
+            square_type: self.square_type,
+            version: self.version.code(),
+            document_type: self.document_type,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Header, Version};
+
+    #[test]
+    fn writes_nibbles_in_order() {
+        let header = Header { square_type: 1, version: Version::V1_2_0, document_type: 3, reserved: 0 };
+
+        assert_eq!(header.to_bytes(), [0x12, 0x30]);
+    }
+
+    #[test]
+    fn reads_nibbles_in_order() {
+        let header = Header::from_bytes([0x11, 0x20]).unwrap();
+
+        assert_eq!(header, Header { square_type: 1, version: Version::V1_1_0, document_type: 2, reserved: 0 });
+        assert_eq!(Header::from_bytes(header.to_bytes()).unwrap(), header);
+    }
+
+    #[test]
+    fn rejects_unknown_version() {
+        assert!(Header::from_bytes([0x03, 0x00]).is_err());
+    }
+
+    #[test]
+    fn parses_version_names() {
+        assert_eq!("1.1.0".parse::<Version>(), Ok(Version::V1_1_0));
+        assert_eq!(Version::V1_2_0.to_string(), "1.2.0");
+        assert!("2.0.0".parse::<Version>().is_err());
+    }
+}
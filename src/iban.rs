@@ -0,0 +1,84 @@
+/// Length of the IBAN per country, according to the ISO 13616 registry.
+const COUNTRY_LENGTHS: [(&str, usize); 88] = [
+    ("AD", 24), ("AE", 23), ("AL", 28), ("AT", 20), ("AZ", 28), ("BA", 20), ("BE", 16), ("BG", 22),
+    ("BH", 22), ("BI", 27), ("BR", 29), ("BY", 28), ("CH", 21), ("CR", 22), ("CY", 28), ("CZ", 24),
+    ("DE", 22), ("DJ", 27), ("DK", 18), ("DO", 28), ("EE", 20), ("EG", 29), ("ES", 24), ("FI", 18),
+    ("FK", 18), ("FO", 18), ("FR", 27), ("GB", 22), ("GE", 22), ("GI", 23), ("GL", 18), ("GR", 27),
+    ("GT", 28), ("HR", 21), ("HU", 28), ("IE", 22), ("IL", 23), ("IQ", 23), ("IS", 26), ("IT", 27),
+    ("JO", 30), ("KW", 30), ("KZ", 20), ("LB", 28), ("LC", 32), ("LI", 21), ("LT", 20), ("LU", 20),
+    ("LV", 21), ("LY", 25), ("MC", 27), ("MD", 24), ("ME", 22), ("MK", 19), ("MN", 20), ("MR", 27),
+    ("MT", 31), ("MU", 30), ("NI", 28), ("NL", 18), ("NO", 15), ("OM", 23), ("PK", 24), ("PL", 28),
+    ("PS", 29), ("PT", 25), ("QA", 29), ("RO", 24), ("RS", 22), ("RU", 33), ("SA", 24), ("SC", 31),
+    ("SD", 18), ("SE", 24), ("SI", 19), ("SK", 24), ("SM", 27), ("SO", 23), ("ST", 25), ("SV", 28),
+    ("TL", 23), ("TN", 24), ("TR", 26), ("UA", 29), ("VA", 22), ("VG", 24), ("XK", 20), ("YE", 30),
+];
+
+/// Convert IBAN to the electronic format, e.g. "sk31 1200 0000 1987 4263 7541" to "SK3112000000198742637541".
+pub fn normalize(iban: &str) -> String {
+    iban.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Expected length of the IBAN issued in the country, None for countries not in the registry.
+pub fn country_length(iban: &str) -> Option<usize> {
+    let country = iban.get(0..2)?;
+
+    COUNTRY_LENGTHS.iter().find(|(code, _)| *code == country).map(|(_, length)| *length)
+}
+
+/// Verify the check digits of the IBAN in electronic format with the ISO 7064 mod 97-10 algorithm.
+pub fn has_valid_checksum(iban: &str) -> bool {
+    if iban.len() < 5 || !iban.is_ascii() {
+        return false;
+    }
+
+    // Country code and check digits are moved to the end, letters are replaced by 10 - 35
+    let rearranged = iban[4..].chars().chain(iban[..4].chars());
+    let mut remainder: u32 = 0;
+
+    for c in rearranged {
+        let value = match c.to_digit(36) {
+            Some(value) => value,
+            None => return false,
+        };
+
+        remainder = if value < 10 { remainder * 10 + value } else { remainder * 100 + value } % 97;
+    }
+
+    remainder == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{country_length, has_valid_checksum, normalize};
+
+    #[test]
+    fn normalizes_to_electronic_format() {
+        assert_eq!(normalize("sk31 1200 0000 1987 4263 7541"), "SK3112000000198742637541");
+    }
+
+    #[test]
+    fn verifies_checksum() {
+        assert!(has_valid_checksum("SK3112000000198742637541"));
+        assert!(has_valid_checksum("SK8811000000002945102347"));
+        assert!(has_valid_checksum("GB29NWBK60161331926819"));
+        assert!(has_valid_checksum("NO9386011117947"));
+
+        assert!(!has_valid_checksum("SK3112000000198742637542"));
+        assert!(!has_valid_checksum("SK1312000000198742637541"));
+        assert!(!has_valid_checksum("SK31-1200"));
+        assert!(!has_valid_checksum("SK3"));
+    }
+
+    #[test]
+    fn finds_country_length() {
+        assert_eq!(country_length("SK3112000000198742637541"), Some(24));
+        assert_eq!(country_length("CZ6508000000192000145399"), Some(24));
+        assert_eq!(country_length("NO9386011117947"), Some(15));
+        assert_eq!(country_length("RU0204452560040702810412345678901"), Some(33));
+        assert_eq!(country_length("US12345678"), None);
+        assert_eq!(country_length("S"), None);
+    }
+}
@@ -0,0 +1,287 @@
+//! Low-level structure of the by square content, for debugging codes which other applications fail to read.
+//! Unlike the decoder, inspection does not stop at a length or checksum mismatch, nor at invalid values.
+
+use crc32fast::Hasher;
+use qrcode::{EcLevel, QrCode, Version as QrVersion};
+
+use crate::decoder::{base32_decode, regroup_to_bytes, DecodeError};
+use crate::error::BysqrError;
+use crate::header::Header;
+use crate::lzma::{decompress, decompress_to_end};
+use crate::qr;
+
+/// Field of the sequence, labelled by its name and order in the XSD.
+#[derive(Debug, Clone)]
+pub struct InspectedField {
+    /// Order of the field within its parent elements, for example "2.10.1" for IBAN of the bank account.
+    /// Position in the sequence for documents other than Pay, empty for fields which are not expected
+    /// by the structure of the document.
+    pub order: String,
+    /// Path to the field, for example "Payments.Payment[0].BankAccounts.BankAccount[0].IBAN".
+    /// "?" for fields which are not expected by the structure of the document.
+    pub path: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Inspection {
+    /// Header nibbles in order bysquareType, version, documentType and reserved.
+    pub nibbles: [u8; 4],
+    /// The header, None when the version is not known to this implementation.
+    pub header: Option<Header>,
+    /// Length of the checksum and sequence before compression, as declared in the payload.
+    pub declared_length: usize,
+    /// Length of the checksum and sequence, as decompressed.
+    pub actual_length: usize,
+    /// CRC32 checksum stored in front of the sequence.
+    pub stored_checksum: u32,
+    /// CRC32 checksum computed from the decompressed sequence.
+    pub computed_checksum: u32,
+    /// Size of the LZMA stream, without the header and length of the payload.
+    pub compressed_size: usize,
+    /// The tab separated sequence. Invalid UTF-8 characters are replaced.
+    pub sequence: String,
+    pub fields: Vec<InspectedField>,
+    /// Version of the QR code as chosen by qr::create_pay_svg, None when the content does not fit into a QR code.
+    pub qr_version: Option<QrVersion>,
+    /// Error correction level of the QR code as chosen by qr::create_pay_svg.
+    pub qr_error_correction: Option<EcLevel>,
+}
+
+impl Inspection {
+    pub fn length_matches(&self) -> bool {
+        self.declared_length == self.actual_length
+    }
+
+    pub fn checksum_matches(&self) -> bool {
+        self.stored_checksum == self.computed_checksum
+    }
+}
+
+/// Reads fields of the sequence in order, recording them with their labels.
+struct Labeller<'a> {
+    values: std::str::Split<'a, char>,
+    fields: Vec<InspectedField>,
+}
+
+impl<'a> Labeller<'a> {
+    fn next(&mut self, order: String, path: String) -> Option<&'a str> {
+        let value = self.values.next()?;
+        self.fields.push(InspectedField { order, path, value: value.to_string() });
+
+        Some(value)
+    }
+
+    fn next_count(&mut self, order: String, path: String) -> usize {
+        self.next(order, path).and_then(|value| value.parse().ok()).unwrap_or(0)
+    }
+
+    fn next_fields(&mut self, order: &str, path: &str, names: &[&str]) {
+        for (index, name) in names.iter().enumerate() {
+            self.next(format!("{}.{}", order, index + 1), format!("{}.{}", path, name));
+        }
+    }
+
+    /// Record the remaining values, which do not belong to any field.
+    fn finish(mut self) -> Vec<InspectedField> {
+        while self.next(String::new(), String::from("?")).is_some() {}
+
+        self.fields
+    }
+}
+
+const PAYMENT_FIELDS: [&str; 9] = [
+    "PaymentOptions",
+    "Amount",
+    "CurrencyCode",
+    "PaymentDueDate",
+    "VariableSymbol",
+    "ConstantSymbol",
+    "SpecificSymbol",
+    "OriginatorsReferenceInformation",
+    "PaymentNote",
+];
+
+const STANDING_ORDER_FIELDS: [&str; 4] = ["Day", "Month", "Periodicity", "LastDate"];
+
+const DIRECT_DEBIT_FIELDS: [&str; 10] = [
+    "DirectDebitScheme",
+    "DirectDebitType",
+    "VariableSymbol",
+    "SpecificSymbol",
+    "OriginatorsReferenceInformation",
+    "MandateID",
+    "CreditorID",
+    "ContractID",
+    "MaxAmount",
+    "ValidTillDate",
+];
+
+const BENEFICIARY_FIELDS: [(&str, usize); 3] =
+    [("BeneficiaryName", 13), ("BeneficiaryAddressLine1", 14), ("BeneficiaryAddressLine2", 15)];
+
+/// Label fields of the Pay, following the same structure as the decoder.
+fn label_pay(seq: &str) -> Vec<InspectedField> {
+    let mut labeller = Labeller { values: seq.split('\t'), fields: Vec::new() };
+
+    // InvoiceID = order 1
+    labeller.next(String::from("1"), String::from("InvoiceID"));
+
+    // Payments = order 2
+    for index in 0..labeller.next_count(String::from("2"), String::from("Payments")) {
+        let path = format!("Payments.Payment[{}]", index);
+
+        // PaymentOptions to PaymentNote = order 1 to 9
+        labeller.next_fields("2", &path, &PAYMENT_FIELDS);
+
+        // Bank Accounts = order 10
+        for account in 0..labeller.next_count(String::from("2.10"), format!("{}.BankAccounts", path)) {
+            labeller.next_fields("2.10", &format!("{}.BankAccounts.BankAccount[{}]", path, account), &["IBAN", "BIC"]);
+        }
+
+        // Standing Order Extension = order 11
+        if labeller.next(String::from("2.11"), format!("{}.StandingOrderExt", path)) == Some("1") {
+            labeller.next_fields("2.11", &format!("{}.StandingOrderExt", path), &STANDING_ORDER_FIELDS);
+        }
+
+        // Direct Debit Extension = order 12
+        if labeller.next(String::from("2.12"), format!("{}.DirectDebitExt", path)) == Some("1") {
+            labeller.next_fields("2.12", &format!("{}.DirectDebitExt", path), &DIRECT_DEBIT_FIELDS);
+        }
+
+        // Beneficiary = order 13 to 15, missing in codes of version 1.0.0
+        for (name, order) in BENEFICIARY_FIELDS {
+            labeller.next(format!("2.{}", order), format!("{}.{}", path, name));
+        }
+    }
+
+    labeller.finish()
+}
+
+/// Inspect the by square content. Fields are labelled only for PAY by square, the fields of other
+/// documents are listed with their position in the sequence and without path.
+/// Fails when the content can not be decompressed at all.
+pub fn inspect(content: &str) -> Result<Inspection, BysqrError> {
+    let payload = regroup_to_bytes(&base32_decode(content)?);
+
+    if payload.len() < 4 {
+        return Err(DecodeError::PayloadTooShort.into());
+    }
+
+    let nibbles = [payload[0] >> 4, payload[0] & 0b1111, payload[1] >> 4, payload[1] & 0b1111];
+    let header = Header::from_bytes([payload[0], payload[1]]).ok();
+
+    let declared_length = u16::from_le_bytes([payload[2], payload[3]]) as usize;
+
+    // Streams without the end marker can be decompressed only up to the declared length
+    let decompressed = decompress_to_end(&payload[4..])
+        .or_else(|_| decompress(&payload[4..], declared_length))
+        .map_err(|e| DecodeError::Decompression(e.to_string()))?;
+
+    if decompressed.len() < 4 {
+        return Err(DecodeError::PayloadTooShort.into());
+    }
+
+    let stored_checksum = u32::from_le_bytes([decompressed[0], decompressed[1], decompressed[2], decompressed[3]]);
+
+    let mut hasher = Hasher::new();
+    hasher.update(&decompressed[4..]);
+    let computed_checksum = hasher.finalize();
+
+    let sequence = String::from_utf8_lossy(&decompressed[4..]).to_string();
+
+    let fields = match header {
+        Some(Header { square_type: 0, document_type: 0, .. }) => label_pay(&sequence),
+        _ => sequence
+            .split('\t')
+            .enumerate()
+            .map(|(index, value)| InspectedField {
+                order: format!("{}", index + 1),
+                path: String::new(),
+                value: value.to_string(),
+            })
+            .collect(),
+    };
+
+    let code: Option<QrCode> = qr::create_code(content.trim()).ok();
+
+    Ok(Inspection {
+        nibbles,
+        header,
+        declared_length,
+        actual_length: decompressed.len(),
+        stored_checksum,
+        computed_checksum,
+        compressed_size: payload.len() - 4,
+        sequence,
+        fields,
+        qr_version: code.as_ref().map(|code| code.version()),
+        qr_error_correction: code.as_ref().map(|code| code.error_correction_level()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crc32fast::Hasher;
+
+    use super::{inspect, InspectedField};
+    use crate::decoder::{decode, DecodeError};
+    use crate::encoder::{pay_to_seq, payload_to_base32};
+    use crate::error::BysqrError;
+    use crate::header::Header;
+    use crate::lzma::compress;
+    use crate::models::try_deserialize_pay;
+
+    const CONTENT: &str = "0005M000B8EL2I2FP2PSOB3ENDMC9PB3SESUJ694JE1139U2J1Q25BF5OQ2D1CB3RFMNVV4J4VVG5IKNTC41O399RIJH2DHL5SIKOUR5JPCOAQDROPLVVVR5VT000";
+
+    fn field<'a>(fields: &'a [InspectedField], order: &str) -> &'a InspectedField {
+        fields.iter().find(|field| field.order == order).unwrap()
+    }
+
+    #[test]
+    fn inspects_known_content() {
+        let inspection = inspect(CONTENT).unwrap();
+
+        assert_eq!(inspection.nibbles, [0, 0, 0, 0]);
+        assert_eq!(inspection.header, Some(Header::new(0, 0)));
+        assert_eq!(inspection.header.unwrap().version.to_string(), "1.0.0");
+        assert!(inspection.length_matches());
+        assert_eq!(inspection.declared_length, inspection.sequence.len() + 4);
+        assert!(inspection.checksum_matches());
+        assert_eq!(inspection.compressed_size, CONTENT.len() * 5 / 8 - 4);
+
+        let iban = field(&inspection.fields, "2.10.1");
+        assert_eq!(iban.path, "Payments.Payment[0].BankAccounts.BankAccount[0].IBAN");
+        assert_eq!(iban.value, "SK8811000000002945102347");
+        assert_eq!(field(&inspection.fields, "2.3").value, "EUR");
+        assert!(inspection.fields.iter().all(|field| field.path != "?"));
+    }
+
+    #[test]
+    fn reports_corrupted_checksum() {
+        let pay = try_deserialize_pay(include_str!("../example/payment.xml")).unwrap();
+        let seq = pay_to_seq(&pay, &mut Vec::new()).join("\t");
+
+        let mut hasher = Hasher::new();
+        hasher.update(seq.as_bytes());
+        let checksum = hasher.finalize();
+
+        let mut corrupted = Vec::from((checksum ^ 1).to_le_bytes());
+        corrupted.extend_from_slice(seq.as_bytes());
+
+        let mut payload = Vec::from(Header::new(0, 0).to_bytes());
+        payload.extend_from_slice(&(corrupted.len() as u16).to_le_bytes());
+        payload.extend_from_slice(&compress(&corrupted).unwrap());
+        let content = payload_to_base32(&payload);
+
+        assert!(matches!(decode(&content), Err(BysqrError::Decode(DecodeError::ChecksumMismatch { .. }))));
+
+        let inspection = inspect(&content).unwrap();
+
+        assert!(!inspection.checksum_matches());
+        assert_eq!(inspection.stored_checksum, checksum ^ 1);
+        assert_eq!(inspection.computed_checksum, checksum);
+        assert!(inspection.length_matches());
+        assert_eq!(field(&inspection.fields, "2.10.1").value, "SK8811000000002945102347");
+    }
+}
@@ -3,33 +3,48 @@ use wasm_bindgen::prelude::wasm_bindgen;
 #[cfg(feature = "wasm")]
 use crate::models::Pay;
 
+pub mod batch;
+pub mod builder;
+pub mod control;
+pub mod decoder;
 pub mod encoder;
+pub mod error;
+mod field;
+pub mod header;
+pub mod iban;
+pub mod inspect;
+mod lzma;
 pub mod models;
 pub mod qr;
+pub mod scanner;
+pub mod schema;
+pub mod truncation;
+pub mod validator;
+
+#[cfg(feature = "wasm")]
+fn create_svg(source: &str) -> Result<Vec<u8>, error::BysqrError> {
+    let pay: Pay = models::try_deserialize_pay(source)?;
+    let encoded = encoder::encode(&pay)?;
+    qr::create_pay_svg(&encoded, qr::Theme::default())
+}
 
 #[cfg(feature = "wasm")]
 #[wasm_bindgen]
-pub fn encode_to_svg(source: &str) -> String {
-    let pay: Pay = models::try_deserialize_pay(&source);
-    let encoded = encoder::encode(&pay);
-    let svg = qr::create_pay_svg(&encoded, qr::Theme::default());
-    String::from_utf8(svg).unwrap()
+pub fn encode_to_svg(source: &str) -> Result<String, String> {
+    let svg = create_svg(source).map_err(|e| e.to_string())?;
+    String::from_utf8(svg).map_err(|e| e.to_string())
 }
 
 #[cfg(feature = "wasm")]
 #[wasm_bindgen]
-pub fn encode_to_png(source: &str, size: u32) -> String {
-    let pay: Pay = models::try_deserialize_pay(&source);
-    let encoded = encoder::encode(&pay);
-    let svg = qr::create_pay_svg(&encoded, qr::Theme::default());
-    qr::to_base64_png(&svg, size)
+pub fn encode_to_png(source: &str, size: u32) -> Result<String, String> {
+    let svg = create_svg(source).map_err(|e| e.to_string())?;
+    qr::to_base64_png(&svg, size).map_err(|e| e.to_string())
 }
 
 #[cfg(feature = "wasm")]
 #[wasm_bindgen]
-pub fn encode_to_jpeg(source: &str, size: u32, quality: u8) -> String {
-    let pay: Pay = models::try_deserialize_pay(&source);
-    let encoded = encoder::encode(&pay);
-    let svg = qr::create_pay_svg(&encoded, qr::Theme::default());
-    qr::to_base64_jpeg(&svg, size, quality)
+pub fn encode_to_jpeg(source: &str, size: u32, quality: u8) -> Result<String, String> {
+    let svg = create_svg(source).map_err(|e| e.to_string())?;
+    qr::to_base64_jpeg(&svg, size, quality).map_err(|e| e.to_string())
 }
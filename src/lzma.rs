@@ -0,0 +1,110 @@
+//! Raw LZMA1 compression of the payload with the properties required by the specification:
+//! lc=3, lp=0, pb=2 and 128 KiB dictionary. The 13 bytes long header of the LZMA stream
+//! (properties, dictionary size and uncompressed size) is not part of the payload.
+//!
+//! The compression is backed by the liblzma C library by default, or by a pure Rust
+//! implementation with the `lzma-rust` feature.
+
+#[cfg(not(any(feature = "liblzma", feature = "lzma-rust")))]
+compile_error!("one of the features \"liblzma\" or \"lzma-rust\" must be enabled");
+
+const LITERAL_CONTEXT_BITS: u32 = 3;
+const LITERAL_POSITION_BITS: u32 = 0;
+const POSITION_BITS: u32 = 2;
+const DICT_SIZE: u32 = 131072;
+
+#[cfg(feature = "lzma-rust")]
+pub(crate) fn compress(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    use std::io::Write;
+
+    use lzma_rust2::{LzmaOptions, LzmaWriter};
+
+    let mut options = LzmaOptions::with_preset(6);
+    options.lc = LITERAL_CONTEXT_BITS;
+    options.lp = LITERAL_POSITION_BITS;
+    options.pb = POSITION_BITS;
+    options.dict_size = DICT_SIZE;
+
+    // The stream is terminated by the end marker, same as the output of liblzma
+    let mut compressor = LzmaWriter::new_no_header(Vec::new(), &options, true)?;
+    compressor.write_all(data)?;
+
+    compressor.finish()
+}
+
+#[cfg(feature = "lzma-rust")]
+fn decompress_stream(compressed: &[u8], size: u64) -> std::io::Result<Vec<u8>> {
+    use std::io::Read;
+
+    use lzma_rust2::LzmaReader;
+
+    let mut decompressor = LzmaReader::new(
+        compressed,
+        size,
+        LITERAL_CONTEXT_BITS,
+        LITERAL_POSITION_BITS,
+        POSITION_BITS,
+        DICT_SIZE,
+        None,
+    )?;
+
+    let mut decompressed: Vec<u8> = Vec::new();
+    decompressor.read_to_end(&mut decompressed)?;
+
+    Ok(decompressed)
+}
+
+#[cfg(all(feature = "liblzma", not(feature = "lzma-rust")))]
+pub(crate) fn compress(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    use std::io::Write;
+
+    use liblzma::stream::{LzmaOptions, Stream};
+    use liblzma::write::XzEncoder;
+
+    let mut options = LzmaOptions::new_preset(6)?;
+    options.literal_context_bits(LITERAL_CONTEXT_BITS);
+    options.literal_position_bits(LITERAL_POSITION_BITS);
+    options.position_bits(POSITION_BITS);
+    options.dict_size(DICT_SIZE);
+
+    let stream = Stream::new_lzma_encoder(&options)?;
+
+    let mut compressor = XzEncoder::new_stream(Vec::new(), stream);
+    compressor.write_all(data)?;
+
+    Ok(compressor.finish()?.split_off(13))
+}
+
+#[cfg(all(feature = "liblzma", not(feature = "lzma-rust")))]
+fn decompress_stream(compressed: &[u8], size: u64) -> std::io::Result<Vec<u8>> {
+    use std::io::Read;
+
+    use liblzma::read::XzDecoder;
+    use liblzma::stream::Stream;
+
+    // The header stripped by the encoder is put back. Properties byte is computed as (pb * 5 + lp) * 9 + lc.
+    let properties = ((POSITION_BITS * 5 + LITERAL_POSITION_BITS) * 9 + LITERAL_CONTEXT_BITS) as u8;
+
+    let mut stream: Vec<u8> = vec![properties];
+    stream.extend_from_slice(&DICT_SIZE.to_le_bytes());
+    stream.extend_from_slice(&size.to_le_bytes());
+    stream.extend_from_slice(compressed);
+
+    let decoder = Stream::new_lzma_decoder(u64::MAX)?;
+    let mut decompressor = XzDecoder::new_stream(stream.as_slice(), decoder);
+
+    let mut decompressed: Vec<u8> = Vec::new();
+    decompressor.read_to_end(&mut decompressed)?;
+
+    Ok(decompressed)
+}
+
+/// Decompress the payload, knowing the length of the content from the payload header.
+pub(crate) fn decompress(compressed: &[u8], size: usize) -> std::io::Result<Vec<u8>> {
+    decompress_stream(compressed, size as u64)
+}
+
+/// Decompress the payload up to the end marker of the stream, regardless of the declared length.
+pub(crate) fn decompress_to_end(compressed: &[u8]) -> std::io::Result<Vec<u8>> {
+    decompress_stream(compressed, u64::MAX)
+}
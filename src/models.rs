@@ -1,20 +1,38 @@
-use serde::Deserialize;
+use std::fmt;
+use std::ops::BitOr;
+use std::str::FromStr;
 
-#[derive(Debug, Deserialize)]
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::{BysqrError, Rule};
+use crate::field::{self, parse_value};
+use crate::iban;
+use crate::schema;
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct Pay {
+    /// Číslo faktúry v prípade, že údaje sú súčasťou faktúry, alebo identifikátor pre intérne potreby vystavovateľa.
+    /// opt, order = 1, priority = 2, max length 10
+    #[serde(rename = "InvoiceID")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invoice_id: Option<String>,
+
     /// Zoznam jednej alebo viacerých platieb v prípade hromadného príkazu. Hlavná (preferovaná) platba sa uvádza ako prvá.
     pub payments: Payments
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct Payments {
     /// 1+, order = 2
     pub payment: Vec<Payment>
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct Payment {
     /// Možnosti platby sa dajú kombinovať.
@@ -23,7 +41,7 @@ pub struct Payment {
     /// standingorder - trvalý príkaz, údaje sa vyplnia do StandingOrderExt
     /// directdebit - inkaso, údaje sa vyplnia do DirectDebitExt
     /// req, order = 1, priority = 999
-    pub payment_options: String,
+    pub payment_options: PaymentOptions,
 
     /// Čiastka platby. Povolené sú len kladné hodnoty. Desatinná čast je oddelená bodkou.
     /// Môže ostať nevyplnené, napríklad pre dobrovoľný príspevok (donations).
@@ -33,60 +51,755 @@ pub struct Payment {
     /// Desať celých peťdesiat sa uvádza ako "10.5".
     /// Nula celá nula osem sa uvádza ako "0.08".
     /// opt, order = 2, priority = 999
-    pub amount: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<Amount>,
 
     /// Mena platby v ISO 4217 formáte (3 písmená skratka). Príklad: "EUR".
     /// req, order = 3, priority = 999, 3 pismenka ISO, pattern [A-Z]{3}
-    pub currency_code: String,
+    pub currency_code: CurrencyCode,
 
     /// Dátum splatnosti vo formáte ISO 8601 "RRRR-MM-DD". Nepovinný údaj.
     /// V prípade trvalého príkazu označuje dátum prvej platby.
     /// opt, order = 4, priority = 999, datum format
-    pub payment_due_date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "optional_date")]
+    pub payment_due_date: Option<NaiveDate>,
 
     /// Variabilný symbol je maximálne 10 miestne číslo. Nepovinný údaj.
     /// opt, order = 5, priority = 7, max len 10, pattern: [0-9]{0,10}
-    pub variable_symbol: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variable_symbol: Option<VariableSymbol>,
 
     /// Konštantný symbol je 4 miestne identifikačné číslo. Nepovinný údaj.
     /// opt, order = 6, priority = 5, max len 4, pattern: [0-9]{0,4}
-    pub constant_symbol: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub constant_symbol: Option<ConstantSymbol>,
 
     /// Špecifický symbol je maximálne 10 miestne číslo. Nepovinný údaj.
     /// opt, order = 7, priority = 6, max len 10, pattern: [0-9]{0,10}
-    pub specific_symbol: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub specific_symbol: Option<SpecificSymbol>,
 
     /// Referenčná informácia prijímateľa podľa SEPA.
     /// opt, order = 8, priority = 12, max len 35
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub originators_reference_information: Option<String>,
 
     /// Správa pre prijímateľa.
     /// Údaje o platbe, na základe ktorých príjemca bude môcť platbu identifikovať.
     /// Odporúča sa maximálne 140 Unicode znakov.
     /// opt, order = 9, priority = 1, max len 140, unicode
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub payment_note: Option<String>,
 
     /// Zoznam bankových účtov.
     /// req 1+, order = 10
     pub bank_accounts: BankAccounts,
 
-    /// TODO: StandingOrderExt
-    /// TODO: DirectDebitExt
+    /// Rozšírenie platobných údajov o údaje pre nastavenie trvalého príkazu.
+    /// opt, order = 11
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub standing_order_ext: Option<StandingOrderExt>,
+
+    /// Rozšírenie platobných údajov o údaje pre nastavenie a identifikáciu inkasa.
+    /// opt, order = 12
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direct_debit_ext: Option<DirectDebitExt>,
 
     /// Rozšírenie o meno príjemcu
     /// opt, order = 13, priority 999, max length 140
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub beneficiary_name: Option<String>,
 
     /// Rozšírenie o adresu príjemcu
     /// opt, order = 14, priority 999, max length 70
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub beneficiary_address_line_1: Option<String>,
 
     /// Rozšírenie o adresu príjemcu (druhý riadok)
     /// opt, order = 14, priority 999, max length 70
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub beneficiary_address_line_2: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+/// Kombinácia možností platby uložená ako bitová maska (paymentorder = 1, standingorder = 2, directdebit = 4).
+/// Predvolená možnosť je platobný príkaz (paymentorder).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaymentOptions(u8);
+
+/// Jedna z možností platby: platobný príkaz, trvalý príkaz alebo inkaso.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PaymentOption {
+    PaymentOrder,
+    StandingOrder,
+    DirectDebit,
+}
+
+impl PaymentOption {
+    pub const ALL: [PaymentOption; 3] = [PaymentOption::PaymentOrder, PaymentOption::StandingOrder, PaymentOption::DirectDebit];
+
+    /// Name of the option in the XML and JSON documents.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PaymentOption::PaymentOrder => "paymentorder",
+            PaymentOption::StandingOrder => "standingorder",
+            PaymentOption::DirectDebit => "directdebit",
+        }
+    }
+
+    fn bit(&self) -> u8 {
+        match self {
+            PaymentOption::PaymentOrder => 1,
+            PaymentOption::StandingOrder => 2,
+            PaymentOption::DirectDebit => 4,
+        }
+    }
+}
+
+impl From<PaymentOption> for PaymentOptions {
+    fn from(option: PaymentOption) -> Self {
+        PaymentOptions(option.bit())
+    }
+}
+
+impl PaymentOptions {
+    pub const PAYMENT_ORDER: PaymentOptions = PaymentOptions(1);
+    pub const STANDING_ORDER: PaymentOptions = PaymentOptions(2);
+    pub const DIRECT_DEBIT: PaymentOptions = PaymentOptions(4);
+
+    /// Create options from the bitmask used in the sequence.
+    /// Returns None when the mask is empty or contains unknown bits.
+    pub fn from_bits(bits: u8) -> Option<PaymentOptions> {
+        if bits == 0 || bits & !0b111 != 0 {
+            None
+        } else {
+            Some(PaymentOptions(bits))
+        }
+    }
+
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    pub fn contains(&self, other: impl Into<PaymentOptions>) -> bool {
+        let other = other.into();
+
+        self.0 & other.0 == other.0
+    }
+
+    /// Iterate over the options in the order of the specification.
+    pub fn iter(&self) -> impl Iterator<Item = PaymentOption> + '_ {
+        PaymentOption::ALL.into_iter().filter(|option| self.contains(*option))
+    }
+}
+
+impl Default for PaymentOptions {
+    fn default() -> Self {
+        PaymentOptions::PAYMENT_ORDER
+    }
+}
+
+impl BitOr for PaymentOptions {
+    type Output = PaymentOptions;
+
+    fn bitor(self, rhs: PaymentOptions) -> PaymentOptions {
+        PaymentOptions(self.0 | rhs.0)
+    }
+}
+
+impl FromStr for PaymentOptions {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut bits: u8 = 0;
+
+        for name in value.split_whitespace() {
+            match PaymentOption::ALL.iter().find(|option| option.name() == name) {
+                Some(option) => bits |= option.bit(),
+                None => return Err(format!("unknown PaymentOptions value {}", name)),
+            }
+        }
+
+        PaymentOptions::from_bits(bits).ok_or_else(|| String::from("at least one PaymentOptions value is required"))
+    }
+}
+
+impl fmt::Display for PaymentOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self.iter().map(|option| option.name()).collect();
+
+        write!(f, "{}", names.join(" "))
+    }
+}
+
+impl Serialize for PaymentOptions {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PaymentOptions {
+    /// An invalid value is replaced by all of the options while errors are collected, see try_deserialize_pay_lenient.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+
+        parse_value(
+            &value,
+            |value| value.parse().map_err(|_| Rule::OneOf(PaymentOption::ALL.iter().map(|option| option.name()).collect())),
+            || PaymentOptions(0b111),
+        )
+    }
+}
+
+/// Presná suma s najviac dvoma desatinnými miestami, uložená v stotinách (centoch).
+/// Pri deserializácii sa prijíma reťazec aj číslo, napríklad "10.5" alebo 10.5.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Amount(i64);
+
+impl Amount {
+    /// Number of fractional digits allowed in the amount.
+    pub const FRACTION_DIGITS: usize = 2;
+
+    /// Pattern of the amount accepted by FromStr, negative amounts are rejected by the encoder.
+    pub const PATTERN: &'static str = r"^-?\d+(\.\d{1,2})?$";
+
+    pub fn from_minor_units(minor_units: i64) -> Amount {
+        Amount(minor_units)
+    }
+
+    /// The amount in hundredths, e.g. 1099 for "10.99".
+    pub fn minor_units(&self) -> i64 {
+        self.0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0 < 0
+    }
+}
+
+impl FromStr for Amount {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid amount {}", value);
+
+        let (negative, digits) = match value.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, value),
+        };
+
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+
+        if integer.is_empty() || !integer.bytes().all(|b| b.is_ascii_digit()) || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+
+        if digits.contains('.') && fraction.is_empty() {
+            return Err(invalid());
+        }
+
+        if fraction.len() > Amount::FRACTION_DIGITS {
+            return Err(format!("amount {} has more than {} fractional digits", value, Amount::FRACTION_DIGITS));
+        }
+
+        let fraction = format!("{:0<width$}", fraction, width = Amount::FRACTION_DIGITS);
+
+        let minor_units = integer
+            .parse::<i64>()
+            .ok()
+            .and_then(|integer| integer.checked_mul(100))
+            .and_then(|integer| integer.checked_add(fraction.parse::<i64>().ok()?))
+            .ok_or_else(invalid)?;
+
+        Ok(Amount(if negative { -minor_units } else { minor_units }))
+    }
+}
+
+impl fmt::Display for Amount {
+    /// Format without trailing zeros, e.g. "1000", "1.99", "10.5" or "0.08".
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.is_negative() { "-" } else { "" };
+        let units = self.0.unsigned_abs();
+        let (integer, fraction) = (units / 100, units % 100);
+
+        match fraction {
+            0 => write!(f, "{}{}", sign, integer),
+            _ if fraction % 10 == 0 => write!(f, "{}{}.{}", sign, integer, fraction / 10),
+            _ => write!(f, "{}{}.{:02}", sign, integer, fraction),
+        }
+    }
+}
+
+/// Desatinné číslo s ľubovoľnou presnosťou, napríklad kurz meny, množstvo alebo sadzba DPH.
+/// Uchováva sa v textovom tvare bez núl na konci desatinnej časti, napríklad "0.2" alebo "25.3".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decimal(String);
+
+impl Decimal {
+    /// Pattern of the number accepted by FromStr.
+    pub const PATTERN: &'static str = r"^-?\d+(\.\d+)?$";
+
+    pub fn is_negative(&self) -> bool {
+        self.0.starts_with('-')
+    }
+
+    /// Split the number into an integer mantissa and number of fractional digits, "-1.25" is (-125, 2).
+    fn to_parts(&self) -> Option<(i128, u32)> {
+        let (integer, fraction) = self.0.split_once('.').unwrap_or((&self.0, ""));
+        let mantissa: i128 = format!("{}{}", integer, fraction).parse().ok()?;
+
+        Some((mantissa, fraction.len() as u32))
+    }
+
+    fn from_parts(mantissa: i128, scale: u32) -> Decimal {
+        let digits = format!("{:0>width$}", mantissa.unsigned_abs(), width = scale as usize + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - scale as usize);
+        let sign = if mantissa < 0 { "-" } else { "" };
+
+        format!("{}{}.{}", sign, integer, fraction).trim_end_matches('.').parse().expect("valid decimal number")
+    }
+
+    /// Exact sum of the numbers. Returns None on overflow.
+    pub fn checked_add(&self, other: &Decimal) -> Option<Decimal> {
+        let (left, left_scale) = self.to_parts()?;
+        let (right, right_scale) = other.to_parts()?;
+        let scale = left_scale.max(right_scale);
+
+        let left = left.checked_mul(10i128.checked_pow(scale - left_scale)?)?;
+        let right = right.checked_mul(10i128.checked_pow(scale - right_scale)?)?;
+
+        Some(Decimal::from_parts(left.checked_add(right)?, scale))
+    }
+
+    /// Exact product of the numbers. Returns None on overflow.
+    pub fn checked_mul(&self, other: &Decimal) -> Option<Decimal> {
+        let (left, left_scale) = self.to_parts()?;
+        let (right, right_scale) = other.to_parts()?;
+
+        Some(Decimal::from_parts(left.checked_mul(right)?, left_scale + right_scale))
+    }
+}
+
+impl Default for Decimal {
+    fn default() -> Self {
+        Decimal(String::from("0"))
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match value.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, value),
+        };
+
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+
+        if integer.is_empty()
+            || !integer.bytes().all(|b| b.is_ascii_digit())
+            || !fraction.bytes().all(|b| b.is_ascii_digit())
+            || (digits.contains('.') && fraction.is_empty())
+        {
+            return Err(format!("invalid decimal number {}", value));
+        }
+
+        let integer = integer.trim_start_matches('0');
+        let integer = if integer.is_empty() { "0" } else { integer };
+        let fraction = fraction.trim_end_matches('0');
+
+        let mut normalized = String::new();
+
+        if negative && (integer != "0" || !fraction.is_empty()) {
+            normalized.push('-');
+        }
+
+        normalized.push_str(integer);
+
+        if !fraction.is_empty() {
+            normalized.push('.');
+            normalized.push_str(fraction);
+        }
+
+        Ok(Decimal(normalized))
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Serialize for Decimal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Decimal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = deserializer.deserialize_any(NumberVisitor)?;
+
+        parse_value(&value, |value| value.parse().map_err(|_| Rule::Pattern(Decimal::PATTERN)), Decimal::default)
+    }
+}
+
+/// Reads a number either from a string or a JSON number, keeping the textual form for exact parsing.
+struct NumberVisitor;
+
+impl<'de> serde::de::Visitor<'de> for NumberVisitor {
+    type Value = String;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a decimal number")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<String, E> {
+        Ok(value.trim().to_string())
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<String, E> {
+        Ok(value.to_string())
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<String, E> {
+        Ok(value.to_string())
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, value: f64) -> Result<String, E> {
+        if !value.is_finite() {
+            return Err(E::custom(format!("invalid decimal number {}", value)));
+        }
+
+        // The shortest representation of the float is the number as written in the JSON source
+        Ok(value.to_string())
+    }
+
+    /// XML elements are deserialized as a map, the content is under the "$text" key.
+    fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<String, A::Error> {
+        let mut number = None;
+
+        while let Some((key, value)) = map.next_entry::<String, String>()? {
+            if key == "$text" {
+                number = Some(value.trim().to_string());
+            }
+        }
+
+        number.ok_or_else(|| serde::de::Error::custom("missing number"))
+    }
+}
+
+/// Number written either as a string or as a JSON number, kept in the textual form.
+struct Number(String);
+
+impl<'de> Deserialize<'de> for Number {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(NumberVisitor).map(Number)
+    }
+}
+
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = deserializer.deserialize_any(NumberVisitor)?;
+
+        parse_value(&value, |value| value.parse().map_err(|_| Rule::Pattern(Amount::PATTERN)), Amount::default)
+    }
+}
+
+/// Parse the value of a constrained field, reporting the violated rule with the value.
+/// While errors are collected, an invalid value is replaced by `placeholder`.
+fn deserialize_parsed<'de, D, T>(deserializer: D, placeholder: impl FnOnce(&str) -> T) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr<Err = Rule>,
+{
+    let value = String::deserialize(deserializer)?;
+
+    parse_value(&value, str::parse, || placeholder(&value))
+}
+
+/// Check the value has only ASCII digits and at most `max_length` of them.
+fn check_digits(value: &str, max_length: usize, pattern: &'static str) -> Result<(), Rule> {
+    if value.len() <= max_length && value.bytes().all(|b| b.is_ascii_digit()) {
+        Ok(())
+    } else {
+        Err(Rule::Pattern(pattern))
+    }
+}
+
+/// Implement as_str, Display, TryFrom<&str>, Serialize and Deserialize for the newtype over String,
+/// which is validated by its FromStr implementation returning the violated Rule.
+macro_rules! string_newtype {
+    ($name:ident) => {
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+
+            /// Keeps the invalid value in place of a valid one, so the fields following it keep their paths.
+            pub(crate) fn placeholder(value: &str) -> Self {
+                $name(value.to_string())
+            }
+        }
+
+        impl TryFrom<&str> for $name {
+            type Error = Rule;
+
+            fn try_from(value: &str) -> Result<Self, Self::Error> {
+                value.parse()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserialize_parsed(deserializer, $name::placeholder)
+            }
+        }
+    };
+}
+
+/// Medzinárodné číslo bankového účtu v elektronickom formáte, napríklad "SK8209000000000011424060".
+/// Pri vytvorení sa odstránia medzery, písmená sa zmenia na veľké a overí sa dĺžka pre krajinu a kontrolné číslice.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Iban(String);
+
+string_newtype!(Iban);
+
+impl Iban {
+    const PATTERN: &'static str = r"^[A-Z]{2}\d{2}[A-Z\d]{0,30}$";
+}
+
+impl FromStr for Iban {
+    type Err = Rule;
+
+    /// Accept IBAN with spaces or lowercase letters, e.g. "sk82 0900 0000 0000 1142 4060".
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let normalized = iban::normalize(value);
+
+        if !Regex::new(Iban::PATTERN).unwrap().is_match(&normalized) {
+            return Err(Rule::Pattern(Iban::PATTERN));
+        }
+
+        if let Some(length) = iban::country_length(&normalized) {
+            if normalized.len() != length {
+                return Err(Rule::Length(length));
+            }
+        }
+
+        if !iban::has_valid_checksum(&normalized) {
+            return Err(Rule::Checksum);
+        }
+
+        Ok(Iban(normalized))
+    }
+}
+
+/// Medzinárodný bankový identifikačný kód, 8 alebo 11 znakov, napríklad "TATRSKBX".
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Bic(String);
+
+string_newtype!(Bic);
+
+impl Bic {
+    const PATTERN: &'static str = r"^[A-Z]{4}[A-Z]{2}[A-Z\d]{2}([A-Z\d]{3})?$";
+}
+
+impl FromStr for Bic {
+    type Err = Rule;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if Regex::new(Bic::PATTERN).unwrap().is_match(value) {
+            Ok(Bic(value.to_string()))
+        } else {
+            Err(Rule::Pattern(Bic::PATTERN))
+        }
+    }
+}
+
+/// Mena v ISO 4217 formáte (3 písmená), napríklad "EUR". Predvolená mena je euro.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CurrencyCode(String);
+
+string_newtype!(CurrencyCode);
+
+impl CurrencyCode {
+    const PATTERN: &'static str = "^[A-Z]{3}$";
+}
+
+impl Default for CurrencyCode {
+    fn default() -> Self {
+        CurrencyCode(String::from("EUR"))
+    }
+}
+
+impl FromStr for CurrencyCode {
+    type Err = Rule;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.len() == 3 && value.bytes().all(|b| b.is_ascii_uppercase()) {
+            Ok(CurrencyCode(value.to_string()))
+        } else {
+            Err(Rule::Pattern(CurrencyCode::PATTERN))
+        }
+    }
+}
+
+/// Krajina v ISO 3166 formáte (3 písmená), napríklad "SVK". Predvolená krajina je Slovensko.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CountryCode(String);
+
+string_newtype!(CountryCode);
+
+impl CountryCode {
+    const PATTERN: &'static str = "^[A-Z]{3}$";
+}
+
+impl Default for CountryCode {
+    fn default() -> Self {
+        CountryCode(String::from("SVK"))
+    }
+}
+
+impl FromStr for CountryCode {
+    type Err = Rule;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.len() == 3 && value.bytes().all(|b| b.is_ascii_uppercase()) {
+            Ok(CountryCode(value.to_string()))
+        } else {
+            Err(Rule::Pattern(CountryCode::PATTERN))
+        }
+    }
+}
+
+/// Variabilný symbol, najviac 10 číslic.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VariableSymbol(String);
+
+string_newtype!(VariableSymbol);
+
+impl FromStr for VariableSymbol {
+    type Err = Rule;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        check_digits(value, 10, r"^\d{0,10}$").map(|_| VariableSymbol(value.to_string()))
+    }
+}
+
+/// Konštantný symbol, najviac 4 číslice.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ConstantSymbol(String);
+
+string_newtype!(ConstantSymbol);
+
+impl FromStr for ConstantSymbol {
+    type Err = Rule;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        check_digits(value, 4, r"^\d{0,4}$").map(|_| ConstantSymbol(value.to_string()))
+    }
+}
+
+/// Špecifický symbol, najviac 10 číslic.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SpecificSymbol(String);
+
+string_newtype!(SpecificSymbol);
+
+impl FromStr for SpecificSymbol {
+    type Err = Rule;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        check_digits(value, 10, r"^\d{0,10}$").map(|_| SpecificSymbol(value.to_string()))
+    }
+}
+
+/// Dates are written as "YYYY-MM-DD", the "YYYYMMDD" format of the sequence is accepted too.
+pub(crate) mod optional_date {
+    use chrono::NaiveDate;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::error::Rule;
+    use crate::field::parse_value;
+
+    pub(crate) fn parse(value: &str) -> Result<NaiveDate, Rule> {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .or_else(|_| NaiveDate::parse_from_str(value, "%Y%m%d"))
+            .map_err(|_| Rule::Date)
+    }
+
+    pub fn serialize<S: Serializer>(value: &Option<NaiveDate>, serializer: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(date) => serializer.collect_str(&date.format("%Y-%m-%d")),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<NaiveDate>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(value) => parse_value(value.trim(), parse, NaiveDate::default).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+pub(crate) mod date {
+    use chrono::NaiveDate;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::optional_date;
+    use crate::field::parse_value;
+
+    pub fn serialize<S: Serializer>(value: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&value.format("%Y-%m-%d"))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDate, D::Error> {
+        let value = String::deserialize(deserializer)?;
+
+        parse_value(value.trim(), optional_date::parse, NaiveDate::default)
+    }
+}
+
+/// Day of the standing order, written either as a string or as a JSON number.
+fn deserialize_day<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u16>, D::Error> {
+    let Some(Number(value)) = Option::<Number>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+
+    let max_day = Periodicity::Annually.max_day() as u32;
+
+    parse_value(&value, |value| value.parse().map_err(|_| Rule::Range(1, max_day)), || 0).map(Some)
+}
+
+/// An empty Month is written by encoders which do not use the months, it is read as no months.
+fn deserialize_months<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Months>, D::Error> {
+    match Option::<String>::deserialize(deserializer)? {
+        Some(value) if !value.trim().is_empty() => parse_value(value.trim(), str::parse, || Months(0)).map(Some),
+        _ => Ok(None),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 // Údaje bankového účtu prijímateľa platby.
 pub struct BankAccount {
@@ -95,27 +808,1318 @@ pub struct BankAccount {
     /// Viac na http://www.sbaonline.sk/sk/projekty/financne-vzdelavanie/slovnik-bankovych-pojmov/iii/.
     /// req, order = 1, priority = 999, pattern: [A-Z]{2}[0-9]{2}[A-Z0-9]{0,30}, max length 34
     #[serde(rename = "IBAN")]
-    pub iban: String,
+    pub iban: Iban,
 
     /// Medzinárodný bankový identifikačný kód (z ang. Bank Identification Code).
     /// Viac na http://www.sbaonline.sk/sk/projekty/financne-vzdelavanie/slovnik-bankovych-pojmov/bbb/bic.html.
     /// opt, order = 2, priority = 999
     #[serde(rename = "BIC")]
-    pub bic: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bic: Option<Bic>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct BankAccounts {
     pub bank_account: Vec<BankAccount>
 }
 
-pub fn try_deserialize_pay(content: &str) -> Pay {
-    if content.trim_start().starts_with("<?xml") {
-        quick_xml::de::from_str(&content).expect("unable to parse source as XML")
-    } else if content.trim_start().starts_with("{") {
-        serde_json::from_str(&content).expect("unable to parse source as JSON")
-    } else {
-        panic!("unable to deserialize source as PAY")
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+// Rozšírenie platobných údajov o údaje pre nastavenie trvalého príkazu.
+pub struct StandingOrderExt {
+    /// Deň platby vyplývajúci z opakovania (Periodicity).
+    /// Deň v mesiaci je číslo medzi 1 a 31.
+    /// Deň v týždni je číslo medzi 1 a 7 (1 = pondelok, 2 =utorok, …, 7 = nedeľa).
+    /// Pri ročnom opakovaní je to deň v roku, číslo medzi 1 a 366.
+    /// opt, order = 1, priority = 999, max length 2
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "deserialize_day")]
+    pub day: Option<u16>,
+
+    /// Medzerou oddelený zoznam mesiacov, v ktoré sa má platba uskutočniť.
+    /// Príklad: "January April July October".
+    /// opt, order = 2, priority = 999, max length 4
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "deserialize_months")]
+    pub month: Option<Months>,
+
+    /// Opakovanie (periodicita) trvalého príkazu.
+    /// Daily, Weekly, Biweekly, Monthly, Bimonthly, Quarterly, Annually, Semiannually
+    /// req, order = 3, priority = 999, max length 1
+    pub periodicity: Periodicity,
+
+    /// Dátum poslednej platby v trvalom príkaze.
+    /// opt, order = 4, priority = 999, datum format
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "optional_date")]
+    pub last_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+// Rozšírenie platobných údajov o údaje pre nastavenie a identifikáciu inkasa.
+pub struct DirectDebitExt {
+    /// Inkasná schéma. Uvádza ja jedna z možností:
+    /// SEPA - Inkaso zodpovedá schéme SEPA.
+    /// other - iné
+    /// req, order = 1, priority = 999, max length 1
+    pub direct_debit_scheme: DirectDebitScheme,
+
+    /// Typ inkasa. Uvádza ja jedna z možností:
+    /// one-off - jednorázové inkaso
+    /// recurrent - opakované inkaso
+    /// req, order = 2, priority = 999, max length 1
+    pub direct_debit_type: DirectDebitType,
+
+    /// Variabilný symbol. Vypĺňa sa len v prípade, ak sa odlišuje od variabilného symbolu v platobnom príkaze.
+    /// opt, order = 3, priority = 4, max len 10, pattern: [0-9]{0,10}
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variable_symbol: Option<VariableSymbol>,
+
+    /// Špecifický symbol. Vypĺňa sa len v prípade, ak sa odlišuje od špecifického symbolu v platobnom príkaze.
+    /// opt, order = 4, priority = 3, max len 10, pattern: [0-9]{0,10}
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub specific_symbol: Option<SpecificSymbol>,
+
+    /// Referenčná informácia. Použije sa len na prechodné obdobie z variabilného a špecifického symbolu na SEPA inkaso.
+    /// opt, order = 5, priority = 11, max len 35
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub originators_reference_information: Option<String>,
+
+    /// Identifikácia mandátu medzi veriteľom a dlžníkom podľa SEPA.
+    /// opt, order = 6, priority = 10, max len 35
+    #[serde(rename = "MandateID")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mandate_id: Option<String>,
+
+    /// Identifikácia veriteľa podľa SEPA.
+    /// opt, order = 7, priority = 9, max len 35
+    #[serde(rename = "CreditorID")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub creditor_id: Option<String>,
+
+    /// Identifikácia zmluvy medzi veriteľom a dlžníkom podľa SEPA.
+    /// opt, order = 8, priority = 8, max len 35
+    #[serde(rename = "ContractID")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contract_id: Option<String>,
+
+    /// Maximálna čiastka inkasa.
+    /// opt, order = 9, priority = 999, max len 15
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_amount: Option<Amount>,
+
+    /// Dátum platnosti inkasa. Platnosť inkasa zaníka dňom tohto dátumu.
+    /// opt, order = 10, priority = 999, datum format
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "optional_date")]
+    pub valid_till_date: Option<NaiveDate>,
+}
+
+/// Implement ALL, name, FromStr, Display, Serialize and Deserialize for the enumeration of the XSD,
+/// from the name of each value in the XML and JSON documents.
+macro_rules! named_enum {
+    ($name:ident { $($variant:ident => $value:literal),+ $(,)? }) => {
+        impl $name {
+            pub const ALL: &'static [$name] = &[$($name::$variant),+];
+
+            /// Name of the value in the XML and JSON documents.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $value),+
+                }
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = Rule;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                $name::ALL
+                    .iter()
+                    .find(|item| item.name() == value)
+                    .copied()
+                    .ok_or_else(|| Rule::OneOf($name::ALL.iter().map(|item| item.name()).collect()))
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.name())
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserialize_parsed(deserializer, |_| $name::ALL[0])
+            }
+        }
+    };
+}
+
+/// Inkasná schéma: SEPA alebo iná.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DirectDebitScheme {
+    Other,
+    #[default]
+    Sepa,
+}
+
+named_enum!(DirectDebitScheme { Other => "other", Sepa => "SEPA" });
+
+impl DirectDebitScheme {
+    /// Code of the scheme in the sequence, 0 for other and 1 for SEPA.
+    pub fn code(&self) -> u8 {
+        *self as u8
+    }
+
+    pub fn from_code(code: u8) -> Option<DirectDebitScheme> {
+        DirectDebitScheme::ALL.get(code as usize).copied()
+    }
+}
+
+/// Typ inkasa: jednorázové alebo opakované.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DirectDebitType {
+    #[default]
+    OneOff,
+    Recurrent,
+}
+
+named_enum!(DirectDebitType { OneOff => "one-off", Recurrent => "recurrent" });
+
+impl DirectDebitType {
+    /// Code of the type in the sequence, 0 for one-off and 1 for recurrent.
+    pub fn code(&self) -> u8 {
+        *self as u8
+    }
+
+    pub fn from_code(code: u8) -> Option<DirectDebitType> {
+        DirectDebitType::ALL.get(code as usize).copied()
+    }
+}
+
+/// Opakovanie (periodicita) trvalého príkazu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Periodicity {
+    Daily,
+    Weekly,
+    Biweekly,
+    #[default]
+    Monthly,
+    Bimonthly,
+    Quarterly,
+    Annually,
+    Semiannually,
+}
+
+named_enum!(Periodicity {
+    Daily => "Daily",
+    Weekly => "Weekly",
+    Biweekly => "Biweekly",
+    Monthly => "Monthly",
+    Bimonthly => "Bimonthly",
+    Quarterly => "Quarterly",
+    Annually => "Annually",
+    Semiannually => "Semiannually",
+});
+
+impl Periodicity {
+    /// Code of the periodicity in the sequence.
+    pub fn code(&self) -> char {
+        match self {
+            Periodicity::Daily => 'd',
+            Periodicity::Weekly => 'w',
+            Periodicity::Biweekly => 'b',
+            Periodicity::Monthly => 'm',
+            Periodicity::Bimonthly => 'B',
+            Periodicity::Quarterly => 'q',
+            Periodicity::Annually => 'a',
+            Periodicity::Semiannually => 's',
+        }
+    }
+
+    pub fn from_code(code: char) -> Option<Periodicity> {
+        Periodicity::ALL.iter().find(|periodicity| periodicity.code() == code).copied()
+    }
+
+    /// Highest Day of the standing order, which is a day of the week for weekly payments,
+    /// a day of the year for annual payments and a day of the month otherwise.
+    pub fn max_day(&self) -> u16 {
+        match self {
+            Periodicity::Weekly | Periodicity::Biweekly => 7,
+            Periodicity::Annually => 366,
+            _ => 31,
+        }
+    }
+}
+
+/// Kalendárny mesiac.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Month {
+    January,
+    February,
+    March,
+    April,
+    May,
+    June,
+    July,
+    August,
+    September,
+    October,
+    November,
+    December,
+}
+
+named_enum!(Month {
+    January => "January",
+    February => "February",
+    March => "March",
+    April => "April",
+    May => "May",
+    June => "June",
+    July => "July",
+    August => "August",
+    September => "September",
+    October => "October",
+    November => "November",
+    December => "December",
+});
+
+impl Month {
+    /// Bit of the month in the mask, January = 1, February = 2, March = 4, ...
+    fn bit(&self) -> u16 {
+        1 << (*self as u16)
+    }
+}
+
+/// Zoznam mesiacov trvalého príkazu uložený ako bitová maska (January = 1, February = 2, March = 4, ...).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Months(u16);
+
+impl From<Month> for Months {
+    fn from(month: Month) -> Self {
+        Months(month.bit())
+    }
+}
+
+impl Months {
+    /// Create months from the bitmask used in the sequence.
+    /// Returns None when the mask is empty or contains unknown bits.
+    pub fn from_bits(bits: u16) -> Option<Months> {
+        if bits == 0 || bits >> Month::ALL.len() != 0 {
+            None
+        } else {
+            Some(Months(bits))
+        }
+    }
+
+    pub fn bits(&self) -> u16 {
+        self.0
+    }
+
+    pub fn contains(&self, month: Month) -> bool {
+        self.0 & month.bit() != 0
+    }
+
+    /// Iterate over the months in the calendar order.
+    pub fn iter(&self) -> impl Iterator<Item = Month> + '_ {
+        Month::ALL.iter().copied().filter(|month| self.contains(*month))
+    }
+}
+
+impl BitOr for Months {
+    type Output = Months;
+
+    fn bitor(self, rhs: Months) -> Months {
+        Months(self.0 | rhs.0)
+    }
+}
+
+impl FromStr for Months {
+    type Err = Rule;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut bits: u16 = 0;
+
+        for name in value.split_whitespace() {
+            bits |= name.parse::<Month>()?.bit();
+        }
+
+        Months::from_bits(bits).ok_or(Rule::Required)
+    }
+}
+
+impl fmt::Display for Months {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self.iter().map(|month| month.name()).collect();
+
+        write!(f, "{}", names.join(" "))
+    }
+}
+
+impl Serialize for Months {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Months {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_parsed(deserializer, |_| Months(0))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Invoice {
+    /// Typ dokumentu faktúry, uvádza sa v atribúte type koreňového elementu.
+    /// Invoice - faktúra, ProformaInvoice - proforma faktúra, CreditNote - dobropis,
+    /// DebitNote - ťarchopis, AdvanceInvoice - zálohová faktúra
+    /// Pri serializácii sa zapisuje do koreňového elementu, pozri Invoice::to_xml a Invoice::to_json.
+    #[serde(rename = "@type", alias = "Type", default, skip_serializing)]
+    pub document_type: InvoiceDocumentType,
+
+    /// Číslo faktúry, jednoznačne identifikuje faktúru v rámci účtovného systému spoločnosti.
+    /// req, order = 1, priority = 21, max length 10
+    #[serde(rename = "InvoiceID")]
+    pub invoice_id: String,
+
+    /// Dátum vystavenia faktúry.
+    /// req, order = 2, priority = 999, datum format
+    #[serde(with = "date")]
+    pub issue_date: NaiveDate,
+
+    /// Dátum zdaniteľného plnenia (vzniku daňovej povinnosti), dodania tovaru alebo služby.
+    /// opt, order = 3, priority = 999, datum format
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "optional_date")]
+    pub tax_point_date: Option<NaiveDate>,
+
+    /// Číslo objednávky. V prípade, že faktúra nadväzuje na viacero objednávok, uvedie sa číslo primárnej objednávky.
+    /// opt, order = 4, priority = 7, max length 10
+    #[serde(rename = "OrderID")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<String>,
+
+    /// Číslo dodacieho listu. V prípade, že faktúra nadväzuje na viacero dodacích listov, uvedie sa číslo primárneho dodacieho listu.
+    /// opt, order = 5, priority = 8, max length 10
+    #[serde(rename = "DeliveryNoteID")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delivery_note_id: Option<String>,
+
+    /// Domáca mena v ISO 4217 formáte (3 písmená skratka). Príklad: "EUR".
+    /// req, order = 6, priority = 999, pattern [A-Z]{3}
+    pub local_currency_code: CurrencyCode,
+
+    /// Zahraničná mena v ISO 4217 formáte (3 písmená skratka). Príklad: "USD".
+    /// V prípade, že je uvedená zahraničná mena, všetky čiastky na faktúre sa uvádzajú v zahraničnej mene.
+    /// opt, order = 7, priority = 999, pattern [A-Z]{3}
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub foreign_currency_code: Option<CurrencyCode>,
+
+    /// Kurz zahraničnej meny - priama kotácia, vzhľadom na domácu menu.
+    /// Uvádza sa spolu so zahraničnou menou.
+    /// opt, order = 8, priority = 999, max length 15
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub curr_rate: Option<Decimal>,
+
+    /// Kurz zahraničnej meny - nepriama kotácia, vzhľadom na domácu menu.
+    /// Uvádza sa spolu so zahraničnou menou.
+    /// opt, order = 9, priority = 999, max length 15
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference_curr_rate: Option<Decimal>,
+
+    /// Informácie o dodávateľovi.
+    /// req, order = 10
+    pub supplier_party: SupplierParty,
+
+    /// Informácie o zákazníkovi.
+    /// req, order = 11
+    pub customer_party: CustomerParty,
+
+    /// Počet položiek faktúry. Uvádza sa v prípade viac položkovej faktúry, v prípade hlavičkovej faktúry sa vyplní 0.
+    /// Nesmie byť uvedený spolu so SingleInvoiceLine.
+    /// opt, order = 12, priority = 999, max length 11
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number_of_invoice_lines: Option<u32>,
+
+    /// Všeobecný popis faktúry. Uvádza sa v prípade viacerých položiek, inak musí ostať prázdne.
+    /// opt, order = 13, priority = 1, max length 30
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invoice_description: Option<String>,
+
+    /// Detaily pre jednu položku faktúry. Uvádza sa iba v prípade jedno-položkovej faktúry.
+    /// opt, order = 14
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub single_invoice_line: Option<SingleInvoiceLine>,
+
+    /// Zoznam rekapitulácií jednotlivých daňových sadzieb pre DPH.
+    /// req, order = 15
+    pub tax_category_summaries: TaxCategorySummaries,
+
+    /// Celková rekapitulácia faktúry. Súčet rekapitulácií všetkých sadzieb DPH.
+    /// req, order = 16
+    pub monetary_summary: MonetarySummary,
+
+    /// Formy úhrady sa dajú kombinovať, oddeľujú sa medzerou. Príklad: "moneyTransfer cash creditCard".
+    /// opt, order = 17, priority = 999
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payment_means: Option<PaymentMeans>,
+}
+
+/// Typ dokumentu faktúry, jeho kód sa zapisuje do hlavičky.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InvoiceDocumentType {
+    #[default]
+    Invoice,
+    ProformaInvoice,
+    CreditNote,
+    DebitNote,
+    AdvanceInvoice,
+}
+
+impl InvoiceDocumentType {
+    const NAMES: [(InvoiceDocumentType, &'static str); 5] = [
+        (InvoiceDocumentType::Invoice, "Invoice"),
+        (InvoiceDocumentType::ProformaInvoice, "ProformaInvoice"),
+        (InvoiceDocumentType::CreditNote, "CreditNote"),
+        (InvoiceDocumentType::DebitNote, "DebitNote"),
+        (InvoiceDocumentType::AdvanceInvoice, "AdvanceInvoice"),
+    ];
+
+    /// Document type written into the header, 0 - 4 in the order of the specification.
+    pub fn code(&self) -> u8 {
+        *self as u8
+    }
+
+    pub fn from_code(code: u8) -> Option<InvoiceDocumentType> {
+        InvoiceDocumentType::NAMES.get(code as usize).map(|(document_type, _)| *document_type)
+    }
+}
+
+impl FromStr for InvoiceDocumentType {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        InvoiceDocumentType::NAMES
+            .iter()
+            .find(|(_, name)| *name == value)
+            .map(|(document_type, _)| *document_type)
+            .ok_or_else(|| format!("unknown invoice type {}", value))
+    }
+}
+
+impl fmt::Display for InvoiceDocumentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", InvoiceDocumentType::NAMES[self.code() as usize].1)
+    }
+}
+
+impl Serialize for InvoiceDocumentType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for InvoiceDocumentType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SupplierParty {
+    /// Názov dodávateľa.
+    /// req, order = 1, priority = 17, max length 20
+    pub party_name: String,
+
+    /// Daňové identifikačné číslo (DIČ).
+    /// opt, order = 2, priority = 20, max length 12
+    #[serde(rename = "CompanyTaxID")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub company_tax_id: Option<String>,
+
+    /// Identifikačné číslo pre DPH (IČ DPH).
+    /// opt, order = 3, priority = 19, max length 14
+    #[serde(rename = "CompanyVATID")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub company_vat_id: Option<String>,
+
+    /// Identifikačné číslo organizácie (IČO).
+    /// opt, order = 4, priority = 18, max length 14
+    #[serde(rename = "CompanyRegisterID")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub company_register_id: Option<String>,
+
+    /// Adresa dodávateľa.
+    /// req, order = 5
+    pub postal_address: PostalAddress,
+
+    /// Kontaktná osoba dodávateľa.
+    /// opt, order = 6
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contact: Option<Contact>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CustomerParty {
+    /// Názov zákazníka.
+    /// req, order = 1, priority = 17, max length 20
+    pub party_name: String,
+
+    /// Daňové identifikačné číslo (DIČ).
+    /// opt, order = 2, priority = 20, max length 12
+    #[serde(rename = "CompanyTaxID")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub company_tax_id: Option<String>,
+
+    /// Identifikačné číslo pre DPH (IČ DPH).
+    /// opt, order = 3, priority = 19, max length 14
+    #[serde(rename = "CompanyVATID")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub company_vat_id: Option<String>,
+
+    /// Identifikačné číslo organizácie (IČO).
+    /// opt, order = 4, priority = 18, max length 14
+    #[serde(rename = "CompanyRegisterID")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub company_register_id: Option<String>,
+
+    /// Identifikátor zákazníka v systéme dodávateľa.
+    /// opt, order = 5, priority = 16, max length 20
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub party_identification: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PostalAddress {
+    /// Ulica.
+    /// req, order = 1, priority = 12, max length 20
+    pub street_name: String,
+
+    /// Číslo budovy.
+    /// opt, order = 2, priority = 11, max length 3
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub building_number: Option<String>,
+
+    /// Mesto.
+    /// req, order = 3, priority = 10, max length 20
+    pub city_name: String,
+
+    /// Poštové smerovacie číslo.
+    /// req, order = 4, priority = 9, max length 10
+    pub postal_zone: String,
+
+    /// Štát alebo kraj.
+    /// opt, order = 5, priority = 6, max length 10
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+
+    /// Krajina v ISO 3166 formáte (3 písmená skratka). Príklad: "SVK".
+    /// req, order = 6, priority = 999, pattern [A-Z]{3}
+    pub country: CountryCode,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Contact {
+    /// Meno kontaktnej osoby.
+    /// opt, order = 1, priority = 13, max length 20
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Telefónne číslo.
+    /// opt, order = 2, priority = 14, max length 12
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub telephone: Option<String>,
+
+    /// E-mailová adresa.
+    /// opt, order = 3, priority = 15, max length 40
+    #[serde(rename = "EMail")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SingleInvoiceLine {
+    /// Číslo objednávky pre 1 položku faktúry. Uvádza sa len v prípade, ak je rôzne od čísla hlavnej objednávky na faktúre.
+    /// opt, order = 1, priority = 5, max length 10
+    #[serde(rename = "OrderLineID")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_line_id: Option<String>,
+
+    /// Číslo dodacieho listu pre 1 položku faktúry. Uvádza sa len v prípade, ak je rôzne od čísla hlavného dodacieho listu na faktúre.
+    /// opt, order = 2, priority = 4, max length 10
+    #[serde(rename = "DeliveryNoteLineID")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delivery_note_line_id: Option<String>,
+
+    /// Názov a popis položky. Uvádza sa buď ItemName, alebo ItemEANCode.
+    /// opt, order = 3, priority = 2, max length 30
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_name: Option<String>,
+
+    /// EAN (European Article Number) kód položky.
+    /// opt, order = 4, priority = 3, max length 30
+    #[serde(rename = "ItemEANCode")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_ean_code: Option<String>,
+
+    /// Počiatočný dátum fakturačného obdobia položky. Uvádza sa spolu s PeriodToDate.
+    /// opt, order = 5, priority = 999, datum format
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "optional_date")]
+    pub period_from_date: Option<NaiveDate>,
+
+    /// Konečný dátum fakturačného obdobia položky.
+    /// opt, order = 6, priority = 999, datum format
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "optional_date")]
+    pub period_to_date: Option<NaiveDate>,
+
+    /// Počet kusov (kvantita) tejto položky.
+    /// req, order = 7, priority = 999, max length 15
+    pub invoiced_quantity: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TaxCategorySummaries {
+    /// 1+, order = 15
+    pub tax_category_summary: Vec<TaxCategorySummary>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TaxCategorySummary {
+    /// Sadzba DPH vyjadrená v percentách na intervale 0 až 1. Príklad pre 20% DPH: "0.2".
+    /// req, order = 1, priority = 999, max length 15
+    pub classified_tax_category: Decimal,
+
+    /// Čiastka bez DPH v tejto daňovej sadzbe v domácej mene.
+    /// req, order = 2, priority = 999, max length 15
+    pub tax_exclusive_amount: Amount,
+
+    /// Čiastka DPH v tejto daňovej sadzbe v domácej mene.
+    /// req, order = 3, priority = 999, max length 15
+    pub tax_amount: Amount,
+
+    /// Uhradené zálohy bez DPH v tejto daňovej sadzbe v domácej mene.
+    /// req, order = 4, priority = 999, max length 15
+    pub already_claimed_tax_exclusive_amount: Amount,
+
+    /// DPH z uhradených záloh v tejto daňovej sadzbe v domácej mene.
+    /// req, order = 5, priority = 999, max length 15
+    pub already_claimed_tax_amount: Amount,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MonetarySummary {
+    /// Zaokrúhlenie celkovej sumy v domácej mene. Pripočítava sa k celkovej sume, môže byť záporné.
+    /// Príklad: ak je potrebné čiastku "9.98" zaokrúhliť na "10", uvádza sa hodnota "0.02".
+    /// req, order = 5, priority = 999, max length 15
+    pub payable_rounding_amount: Amount,
+
+    /// Zaplatené nezdanené zálohy v domácej mene. Uvádza sa ako kladné číslo.
+    /// req, order = 6, priority = 999, max length 15
+    pub paid_deposits_amount: Amount,
+}
+
+/// Kombinácia foriem úhrady uložená ako bitová maska (moneyTransfer = 1, cash = 2, cashOnDelivery = 4,
+/// creditCard = 8, advance = 16, mutualOffset = 32, other = 64).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaymentMeans(u8);
+
+impl PaymentMeans {
+    pub const MONEY_TRANSFER: PaymentMeans = PaymentMeans(1);
+    pub const CASH: PaymentMeans = PaymentMeans(2);
+    pub const CASH_ON_DELIVERY: PaymentMeans = PaymentMeans(4);
+    pub const CREDIT_CARD: PaymentMeans = PaymentMeans(8);
+    pub const ADVANCE: PaymentMeans = PaymentMeans(16);
+    pub const MUTUAL_OFFSET: PaymentMeans = PaymentMeans(32);
+    pub const OTHER: PaymentMeans = PaymentMeans(64);
+
+    const NAMES: [(PaymentMeans, &'static str); 7] = [
+        (PaymentMeans::MONEY_TRANSFER, "moneyTransfer"),
+        (PaymentMeans::CASH, "cash"),
+        (PaymentMeans::CASH_ON_DELIVERY, "cashOnDelivery"),
+        (PaymentMeans::CREDIT_CARD, "creditCard"),
+        (PaymentMeans::ADVANCE, "advance"),
+        (PaymentMeans::MUTUAL_OFFSET, "mutualOffset"),
+        (PaymentMeans::OTHER, "other"),
+    ];
+
+    /// Create payment means from the bitmask used in the sequence.
+    /// Returns None when the mask is empty or contains unknown bits.
+    pub fn from_bits(bits: u8) -> Option<PaymentMeans> {
+        if bits == 0 || bits & !0b1111111 != 0 {
+            None
+        } else {
+            Some(PaymentMeans(bits))
+        }
+    }
+
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    pub fn contains(&self, other: PaymentMeans) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl Default for PaymentMeans {
+    fn default() -> Self {
+        PaymentMeans::MONEY_TRANSFER
+    }
+}
+
+impl BitOr for PaymentMeans {
+    type Output = PaymentMeans;
+
+    fn bitor(self, rhs: PaymentMeans) -> PaymentMeans {
+        PaymentMeans(self.0 | rhs.0)
+    }
+}
+
+impl FromStr for PaymentMeans {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut bits: u8 = 0;
+
+        for name in value.split_whitespace() {
+            match PaymentMeans::NAMES.iter().find(|(_, mean)| *mean == name) {
+                Some((mean, _)) => bits |= mean.0,
+                None => return Err(format!("unknown PaymentMeans value {}", name)),
+            }
+        }
+
+        PaymentMeans::from_bits(bits).ok_or_else(|| String::from("at least one PaymentMeans value is required"))
+    }
+}
+
+impl fmt::Display for PaymentMeans {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = PaymentMeans::NAMES
+            .iter()
+            .filter(|(mean, _)| self.contains(*mean))
+            .map(|(_, name)| *name)
+            .collect();
+
+        write!(f, "{}", names.join(" "))
+    }
+}
+
+impl Serialize for PaymentMeans {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PaymentMeans {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct InvoiceItems {
+    /// Číslo faktúry, spája položky s faktúrou v rámci účtovného systému spoločnosti.
+    /// req, order = 1, priority = 8, max length 10
+    #[serde(rename = "InvoiceID")]
+    pub invoice_id: String,
+
+    /// Poradové číslo (identifikátor) prvej položky v tomto zozname položiek.
+    /// Odporúča sa indexovať poradové čísla položiek od jednotky (1), ďalšie položky majú ID 2, 3, 4, ...
+    /// req, order = 2, priority = 7, max length 10
+    #[serde(rename = "FirstInvoiceLineID")]
+    pub first_invoice_line_id: String,
+
+    /// Zoznam jednotlivých riadkov (položiek) faktúry.
+    /// req, order = 3
+    pub invoice_lines: InvoiceLines,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct InvoiceLines {
+    /// 1+, order = 3
+    pub invoice_line: Vec<InvoiceLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct InvoiceLine {
+    /// Identifikácia objednávky tohto riadka (položky).
+    /// opt, order = 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_reference: Option<OrderReference>,
+
+    /// Identifikácia dodacieho listu tohto riadka (položky).
+    /// opt, order = 2
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delivery_note_reference: Option<DeliveryNoteReference>,
+
+    /// Názov a popis položky. Uvádza sa buď ItemName, alebo ItemEANCode.
+    /// opt, order = 3, priority = 5, max length 30
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_name: Option<String>,
+
+    /// EAN (European Article Number) kód položky.
+    /// opt, order = 4, priority = 6, max length 30
+    #[serde(rename = "ItemEANCode")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_ean_code: Option<String>,
+
+    /// Počiatočný dátum fakturačného obdobia položky. Uvádza sa spolu s PeriodToDate.
+    /// opt, order = 5, priority = 999, datum format
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "optional_date")]
+    pub period_from_date: Option<NaiveDate>,
+
+    /// Konečný dátum fakturačného obdobia položky.
+    /// opt, order = 6, priority = 999, datum format
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "optional_date")]
+    pub period_to_date: Option<NaiveDate>,
+
+    /// Počet kusov (kvantita) tejto položky.
+    /// req, order = 7, priority = 999, max length 15
+    pub invoiced_quantity: Decimal,
+
+    /// Jednotková cena položky bez DPH v domácej mene.
+    /// req, order = 8, priority = 999, max length 15
+    pub unit_price_tax_exclusive_amount: Decimal,
+
+    /// Daň z jednotkovej ceny položky v domácej mene.
+    /// req, order = 9, priority = 999, max length 15
+    pub unit_price_tax_amount: Decimal,
+
+    /// Sadzba DPH, do ktorej patrí táto položka, vyjadrená v percentách na intervale 0 až 1. Príklad pre 10% DPH: "0.1".
+    /// req, order = 10, priority = 999, max length 15
+    pub classified_tax_category: Decimal,
+}
+
+impl InvoiceLine {
+    /// Jednotková cena položky s DPH, dopočítava sa ako UnitPriceTaxExclusiveAmount + UnitPriceTaxAmount.
+    pub fn unit_price_tax_inclusive_amount(&self) -> Option<Decimal> {
+        self.unit_price_tax_exclusive_amount.checked_add(&self.unit_price_tax_amount)
+    }
+
+    /// Celková cena riadka bez DPH, dopočítava sa ako UnitPriceTaxExclusiveAmount * InvoicedQuantity.
+    pub fn line_tax_exclusive_amount(&self) -> Option<Decimal> {
+        self.unit_price_tax_exclusive_amount.checked_mul(&self.invoiced_quantity)
+    }
+
+    /// Celková cena riadka s DPH, dopočítava sa ako UnitPriceTaxInclusiveAmount * InvoicedQuantity.
+    pub fn line_tax_inclusive_amount(&self) -> Option<Decimal> {
+        self.unit_price_tax_inclusive_amount()?.checked_mul(&self.invoiced_quantity)
+    }
+
+    /// Daň z celkovej ceny riadka, dopočítava sa ako UnitPriceTaxAmount * InvoicedQuantity.
+    pub fn line_tax_amount(&self) -> Option<Decimal> {
+        self.unit_price_tax_amount.checked_mul(&self.invoiced_quantity)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct OrderReference {
+    /// Číslo objednávky, na ktorej sa nachádza táto položka.
+    /// Uvádza sa len v prípade, ak je rôzne od čísla hlavnej objednávky (OrderID) na faktúre.
+    /// opt, order = 1, priority = 4, max length 10
+    #[serde(rename = "OrderID")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<String>,
+
+    /// Identifikácia tohto riadka (položky) na objednávke.
+    /// opt, order = 2, priority = 3, max length 10
+    #[serde(rename = "OrderLineID")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_line_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeliveryNoteReference {
+    /// Číslo dodacieho listu, na ktorom sa nachádza táto položka.
+    /// Uvádza sa len v prípade, ak je rôzne od čísla hlavného dodacieho listu (DeliveryNoteID) na faktúre.
+    /// opt, order = 1, priority = 2, max length 10
+    #[serde(rename = "DeliveryNoteID")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delivery_note_id: Option<String>,
+
+    /// Identifikácia tohto riadka (položky) na dodacom liste.
+    /// opt, order = 2, priority = 1, max length 10
+    #[serde(rename = "DeliveryNoteLineID")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delivery_note_line_id: Option<String>,
+}
+
+/// Dokument ľubovoľného podporovaného by square typu.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Document {
+    Pay(Pay),
+    Invoice(Box<Invoice>),
+    InvoiceItems(InvoiceItems),
+}
+
+/// Deserialize the document from XML or JSON, detected by the first character of the content. Values which do not
+/// satisfy their type, such as IBAN, symbols or dates, are collected with path of their field, the document is usable
+/// only when there are none. Fails when the document itself can not be read.
+fn try_deserialize_collecting<T: DeserializeOwned>(content: &str) -> Result<(T, Vec<BysqrError>), BysqrError> {
+    if content.trim_start().starts_with("<?xml") {
+        field::collect(|| serde_path_to_error::deserialize(&mut quick_xml::de::Deserializer::from_str(content)))
+    } else if content.trim_start().starts_with("{") {
+        field::collect(|| {
+            let mut deserializer = serde_json::Deserializer::from_str(content);
+            let document = serde_path_to_error::deserialize(&mut deserializer)?;
+
+            // Trailing characters after the document are not part of any field
+            deserializer
+                .end()
+                .map(|_| document)
+                .map_err(|e| serde_path_to_error::Error::new(serde_path_to_error::Track::new().path(), e))
+        })
+    } else {
+        Err(BysqrError::UnknownFormat)
+    }
+}
+
+/// Deserialize the document from XML or JSON, reporting the first value which does not satisfy its type with path of the field.
+fn try_deserialize<T: DeserializeOwned>(content: &str) -> Result<T, BysqrError> {
+    let (document, errors) = try_deserialize_collecting(content)?;
+
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(document),
+    }
+}
+
+/// Deserialize the Pay, reporting the first value which does not satisfy its type with path of the field.
+pub fn try_deserialize_pay(content: &str) -> Result<Pay, BysqrError> {
+    let (pay, errors) = try_deserialize_pay_lenient(content, false)?;
+
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(pay),
+    }
+}
+
+/// Deserialize the Pay like try_deserialize_pay, but reject XML documents with unknown elements or attributes,
+/// missing type="Pay" attribute or elements occurring more or less times than allowed by the specification.
+/// JSON documents are deserialized the same way as by try_deserialize_pay.
+pub fn try_deserialize_pay_strict(content: &str) -> Result<Pay, BysqrError> {
+    let (pay, errors) = try_deserialize_pay_lenient(content, true)?;
+
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(pay),
+    }
+}
+
+/// Deserialize the Pay, collecting errors of all values which do not satisfy their type, such as IBAN,
+/// symbols or dates, with path of the field. Invalid values are replaced by placeholders, so the Pay is
+/// usable only when there are no errors. Fails when the document itself can not be read.
+pub(crate) fn try_deserialize_pay_lenient(content: &str, strict: bool) -> Result<(Pay, Vec<BysqrError>), BysqrError> {
+    if strict && content.trim_start().starts_with("<?xml") {
+        schema::check_pay(content)?;
+    }
+
+    let (mut pay, errors): (Pay, Vec<BysqrError>) = try_deserialize_collecting(content)?;
+
+    // Options implied by the extensions are used when the value is invalid, so the extensions are still checked
+    for (index, payment) in pay.payments.payment.iter_mut().enumerate() {
+        let path = format!("Payments.Payment[{}].PaymentOptions", index);
+
+        if errors.iter().any(|error| matches!(error, BysqrError::InvalidField { path: invalid, .. } if *invalid == path)) {
+            payment.payment_options = match (&payment.standing_order_ext, &payment.direct_debit_ext) {
+                (Some(_), Some(_)) => PaymentOptions::STANDING_ORDER | PaymentOptions::DIRECT_DEBIT,
+                (Some(_), None) => PaymentOptions::STANDING_ORDER,
+                (None, Some(_)) => PaymentOptions::DIRECT_DEBIT,
+                (None, None) => PaymentOptions::default(),
+            };
+        }
+    }
+
+    Ok((pay, errors))
+}
+
+pub fn try_deserialize_invoice(content: &str) -> Result<Invoice, BysqrError> {
+    try_deserialize(content)
+}
+
+pub fn try_deserialize_invoice_items(content: &str) -> Result<InvoiceItems, BysqrError> {
+    try_deserialize(content)
+}
+
+/// Root element of the XML document with the type attribute, e.g. `<Pay type="Pay">`.
+#[derive(Serialize)]
+struct XmlRoot<'a, T> {
+    #[serde(rename = "@type")]
+    document_type: String,
+    #[serde(flatten)]
+    document: &'a T,
+}
+
+/// JSON document with the type, which is the attribute of the root element in XML. Every document has it,
+/// so JSON of the Pay, Invoice and InvoiceItems has the same structure as their XML.
+#[derive(Serialize)]
+struct JsonRoot<'a, T> {
+    #[serde(rename = "Type")]
+    document_type: String,
+    #[serde(flatten)]
+    document: &'a T,
+}
+
+/// Serialize the document to XML with the declaration, which try_deserialize requires to detect the format.
+fn to_xml<T: Serialize>(root: &str, document: &T) -> Result<String, BysqrError> {
+    let mut xml = String::from("<?xml version=\"1.0\"?>\n");
+
+    let mut serializer = quick_xml::se::Serializer::with_root(&mut xml, Some(root))
+        .map_err(|e| BysqrError::Serialize(e.to_string()))?;
+    serializer.indent(' ', 4);
+
+    document.serialize(serializer).map_err(|e| BysqrError::Serialize(e.to_string()))?;
+    xml.push('\n');
+
+    Ok(xml)
+}
+
+fn to_json<T: Serialize>(document: &T) -> Result<String, BysqrError> {
+    serde_json::to_string_pretty(document).map_err(|e| BysqrError::Serialize(e.to_string()))
+}
+
+impl Pay {
+    /// Serialize to XML in the same structure as accepted by try_deserialize_pay.
+    pub fn to_xml(&self) -> Result<String, BysqrError> {
+        to_xml("Pay", &XmlRoot { document_type: String::from("Pay"), document: self })
+    }
+
+    /// Serialize to JSON in the same structure as accepted by try_deserialize_pay.
+    pub fn to_json(&self) -> Result<String, BysqrError> {
+        to_json(&JsonRoot { document_type: String::from("Pay"), document: self })
+    }
+}
+
+impl Invoice {
+    /// Serialize to XML in the same structure as accepted by try_deserialize_invoice.
+    pub fn to_xml(&self) -> Result<String, BysqrError> {
+        to_xml("Invoice", &XmlRoot { document_type: self.document_type.to_string(), document: self })
+    }
+
+    /// Serialize to JSON in the same structure as accepted by try_deserialize_invoice.
+    pub fn to_json(&self) -> Result<String, BysqrError> {
+        to_json(&JsonRoot { document_type: self.document_type.to_string(), document: self })
+    }
+}
+
+impl InvoiceItems {
+    /// Serialize to XML in the same structure as accepted by try_deserialize_invoice_items.
+    pub fn to_xml(&self) -> Result<String, BysqrError> {
+        to_xml("InvoiceItems", &XmlRoot { document_type: String::from("InvoiceItems"), document: self })
+    }
+
+    /// Serialize to JSON in the same structure as accepted by try_deserialize_invoice_items.
+    pub fn to_json(&self) -> Result<String, BysqrError> {
+        to_json(&JsonRoot { document_type: String::from("InvoiceItems"), document: self })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        try_deserialize_invoice, try_deserialize_invoice_items, try_deserialize_pay, try_deserialize_pay_lenient, Amount,
+        Bic, Decimal, PaymentOption, PaymentOptions,
+    };
+    use crate::error::{BysqrError, Rule};
+
+    const PAYMENT: &str = include_str!("../example/payment.xml");
+    const INVOICE: &str = include_str!("../example/invoice.xml");
+    const INVOICE_ITEMS: &str = include_str!("../example/invoice_items.xml");
+
+    fn invalid_field(error: &BysqrError) -> (&str, &str, &Rule) {
+        match error {
+            BysqrError::InvalidField { path, value, rule } => (path, value, rule),
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn parses_amount_in_minor_units() {
+        assert_eq!("1000".parse::<Amount>().unwrap().minor_units(), 100000);
+        assert_eq!("1.99".parse::<Amount>().unwrap().minor_units(), 199);
+        assert_eq!("10.5".parse::<Amount>().unwrap().minor_units(), 1050);
+        assert_eq!("0.08".parse::<Amount>().unwrap().minor_units(), 8);
+        assert_eq!("-12.3".parse::<Amount>().unwrap().minor_units(), -1230);
+    }
+
+    #[test]
+    fn rejects_invalid_amount() {
+        for value in ["", ".5", "10.", "1,5", "1e3", "+1", "12.345", "99999999999999999999"] {
+            assert!(value.parse::<Amount>().is_err(), "{} should be rejected", value);
+        }
+    }
+
+    #[test]
+    fn formats_amount_without_trailing_zeros() {
+        assert_eq!(Amount::from_minor_units(100000).to_string(), "1000");
+        assert_eq!(Amount::from_minor_units(199).to_string(), "1.99");
+        assert_eq!(Amount::from_minor_units(1050).to_string(), "10.5");
+        assert_eq!(Amount::from_minor_units(8).to_string(), "0.08");
+        assert_eq!(Amount::from_minor_units(-5).to_string(), "-0.05");
+    }
+
+    #[test]
+    fn deserializes_amount_from_number_without_rounding() {
+        assert_eq!(serde_json::from_str::<Amount>("10.5").unwrap().minor_units(), 1050);
+        assert_eq!(serde_json::from_str::<Amount>("0.29").unwrap().minor_units(), 29);
+        assert_eq!(serde_json::from_str::<Amount>("\"1.1\"").unwrap().minor_units(), 110);
+        assert!(serde_json::from_str::<Amount>("1.005").is_err());
+    }
+
+    #[test]
+    fn normalizes_decimal() {
+        assert_eq!("007.500".parse::<Decimal>().unwrap().to_string(), "7.5");
+        assert_eq!("-0.0".parse::<Decimal>().unwrap().to_string(), "0");
+        assert_eq!("0.20".parse::<Decimal>().unwrap().to_string(), "0.2");
+        assert!("1.".parse::<Decimal>().is_err());
+        assert!("1,5".parse::<Decimal>().is_err());
+    }
+
+    #[test]
+    fn computes_exact_decimal_arithmetic() {
+        let price: Decimal = "0.1".parse().unwrap();
+        let quantity: Decimal = "3".parse().unwrap();
+
+        assert_eq!(price.checked_mul(&quantity).unwrap().to_string(), "0.3");
+        assert_eq!(price.checked_add(&"0.2".parse().unwrap()).unwrap().to_string(), "0.3");
+        assert_eq!("-1.25".parse::<Decimal>().unwrap().checked_add(&"1.25".parse().unwrap()).unwrap().to_string(), "0");
+    }
+
+    #[test]
+    fn rejects_invalid_values_with_field_path() {
+        let cases = [
+            ("<IBAN>SK8811000000002945102347</IBAN>", "<IBAN>SK0011000000002945102347</IBAN>", "BankAccounts.BankAccount[0].IBAN", "SK0011000000002945102347"),
+            ("<BIC>TATRSKBX</BIC>", "<BIC>TATR</BIC>", "BankAccounts.BankAccount[0].BIC", "TATR"),
+            ("<CurrencyCode>EUR</CurrencyCode>", "<CurrencyCode>eur</CurrencyCode>", "CurrencyCode", "eur"),
+            ("<VariableSymbol>20202020</VariableSymbol>", "<VariableSymbol>2020A</VariableSymbol>", "VariableSymbol", "2020A"),
+        ];
+
+        for (valid, invalid, field, value) in cases {
+            let error = try_deserialize_pay(&PAYMENT.replace(valid, invalid)).unwrap_err();
+            let path = format!("Payments.Payment[0].{}", field);
+
+            assert_eq!(invalid_field(&error).0, path);
+            assert_eq!(invalid_field(&error).1, value);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_json_value_with_field_path() {
+        let json = try_deserialize_pay(PAYMENT).unwrap().to_json().unwrap();
+        let error = try_deserialize_pay(&json.replace("TATRSKBX", "TATR")).unwrap_err();
+
+        assert_eq!(invalid_field(&error), ("Payments.Payment[0].BankAccounts.BankAccount[0].BIC", "TATR", &Rule::Pattern(Bic::PATTERN)));
+    }
+
+    #[test]
+    fn collects_every_invalid_value_in_document_order() {
+        let content = PAYMENT
+            .replace("<Amount>12.34</Amount>", "<Amount>12,34</Amount>")
+            .replace("SK8811000000002945102347", "SK0011000000002945102347")
+            .replace("<CurrencyCode>EUR</CurrencyCode>", "<CurrencyCode>EURO</CurrencyCode>");
+        let (_, errors) = try_deserialize_pay_lenient(&content, false).unwrap();
+        let paths: Vec<&str> = errors.iter().map(|error| invalid_field(error).0).collect();
+
+        assert_eq!(paths, [
+            "Payments.Payment[0].Amount",
+            "Payments.Payment[0].BankAccounts.BankAccount[0].IBAN",
+            "Payments.Payment[0].CurrencyCode",
+        ]);
+        assert_eq!(invalid_field(&errors[1]).2, &Rule::Checksum);
+    }
+
+    #[test]
+    fn rejects_invalid_invoice_codes_with_field_path() {
+        let error = try_deserialize_invoice(&INVOICE.replace("<LocalCurrencyCode>EUR</LocalCurrencyCode>", "<LocalCurrencyCode>E</LocalCurrencyCode>")).unwrap_err();
+        assert_eq!(invalid_field(&error).0, "LocalCurrencyCode");
+
+        let error = try_deserialize_invoice(&INVOICE.replace("<Country>SVK</Country>", "<Country>SK</Country>")).unwrap_err();
+        assert_eq!(invalid_field(&error).0, "SupplierParty.PostalAddress.Country");
+    }
+
+    #[test]
+    fn reports_malformed_document_without_field_path() {
+        let error = try_deserialize_pay(&PAYMENT.replace("</Payments>", "")).unwrap_err();
+        assert!(matches!(error, BysqrError::Deserialize(_)), "unexpected error: {}", error);
+    }
+
+    #[test]
+    fn converts_combined_payment_options_to_bitmask_and_back() {
+        let options: PaymentOptions = "paymentorder standingorder".parse().unwrap();
+
+        assert_eq!(options.bits(), 0b011);
+        assert_eq!(options, PaymentOptions::PAYMENT_ORDER | PaymentOptions::STANDING_ORDER);
+        assert!(options.contains(PaymentOption::StandingOrder) && !options.contains(PaymentOption::DirectDebit));
+        assert_eq!(PaymentOptions::from_bits(options.bits()).unwrap().to_string(), "paymentorder standingorder");
+        assert_eq!("directdebit  paymentorder".parse::<PaymentOptions>().unwrap().to_string(), "paymentorder directdebit");
+    }
+
+    #[test]
+    fn rejects_unknown_payment_option() {
+        assert!("paymentorder cheque".parse::<PaymentOptions>().is_err());
+        assert!("".parse::<PaymentOptions>().is_err());
+        assert_eq!(PaymentOptions::from_bits(0b1000), None);
+
+        let error = try_deserialize_pay(&PAYMENT.replace(">paymentorder<", ">cheque<")).unwrap_err();
+        let names = vec!["paymentorder", "standingorder", "directdebit"];
+
+        assert_eq!(invalid_field(&error), ("Payments.Payment[0].PaymentOptions", "cheque", &Rule::OneOf(names)));
+    }
+
+    #[test]
+    fn deserializes_serialized_documents() {
+        let pay = try_deserialize_pay(PAYMENT).unwrap();
+        assert_eq!(try_deserialize_pay(&pay.to_xml().unwrap()).unwrap(), pay);
+        assert_eq!(try_deserialize_pay(&pay.to_json().unwrap()).unwrap(), pay);
+
+        let invoice = try_deserialize_invoice(INVOICE).unwrap();
+        assert_eq!(try_deserialize_invoice(&invoice.to_xml().unwrap()).unwrap(), invoice);
+        assert_eq!(try_deserialize_invoice(&invoice.to_json().unwrap()).unwrap(), invoice);
+
+        let items = try_deserialize_invoice_items(INVOICE_ITEMS).unwrap();
+        assert_eq!(try_deserialize_invoice_items(&items.to_xml().unwrap()).unwrap(), items);
+        assert_eq!(try_deserialize_invoice_items(&items.to_json().unwrap()).unwrap(), items);
+    }
+
+    #[test]
+    fn writes_type_of_every_document() {
+        let pay = try_deserialize_pay(PAYMENT).unwrap();
+        let invoice = try_deserialize_invoice(INVOICE).unwrap();
+        let items = try_deserialize_invoice_items(INVOICE_ITEMS).unwrap();
+
+        let documents = [
+            (pay.to_xml().unwrap(), pay.to_json().unwrap(), "Pay", "Pay"),
+            (invoice.to_xml().unwrap(), invoice.to_json().unwrap(), "Invoice", "Invoice"),
+            (items.to_xml().unwrap(), items.to_json().unwrap(), "InvoiceItems", "InvoiceItems"),
+        ];
+
+        for (xml, json, root, document_type) in documents {
+            let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+            assert!(xml.contains(&format!("<{} type=\"{}\">", root, document_type)), "{}", xml);
+            assert_eq!(json["Type"], document_type);
+        }
     }
 }
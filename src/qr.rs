@@ -7,6 +7,8 @@ use jpeg_encoder::{ColorType, Encoder};
 use usvg::{Options, Transform, Tree};
 use xmltree::{Element, EmitterConfig};
 
+use crate::error::BysqrError;
+
 pub const CONTAINER_WIDTH: f32 = 512.0;
 pub const CONTAINER_HEIGHT: f32 = 600.0;
 
@@ -18,8 +20,8 @@ pub struct Theme {
     pay_text_color: String,
 }
 
-impl Theme {
-    pub fn default() -> Self {
+impl Default for Theme {
+    fn default() -> Self {
         Self {
             background_color: String::from("#ffffff"),
             outline_color: String::from("#6fa4d7"),
@@ -114,23 +116,24 @@ fn insert_outline(svg: &mut Element, color: &str) {
     svg.children.push(xmltree::XMLNode::Element(path));
 }
 
-fn insert_qr_content(svg: &mut Element, qr: &str) {
-    let qr_svg = Element::parse(qr.as_bytes()).expect("unable to parse SVG content from QR encoder");
+fn insert_qr_content(svg: &mut Element, qr: &str) -> Result<(), BysqrError> {
+    let qr_svg = Element::parse(qr.as_bytes())
+        .map_err(|e| BysqrError::Render(format!("unable to parse SVG content from QR encoder: {}", e)))?;
 
     let qr_width: f32 =  qr_svg.attributes.get("width")
-        .expect("unable to determine SVG content width")
+        .ok_or_else(|| BysqrError::Render(String::from("unable to determine SVG content width")))?
         .parse()
-        .expect("unable to parse SVG content width as number");
+        .map_err(|_| BysqrError::Render(String::from("unable to parse SVG content width as number")))?;
 
     let qr_height: f32 =  qr_svg.attributes.get("height")
-        .expect("unable to determine SVG content height")
+        .ok_or_else(|| BysqrError::Render(String::from("unable to determine SVG content height")))?
         .parse()
-        .expect("unable to parse SVG content height as number");
+        .map_err(|_| BysqrError::Render(String::from("unable to parse SVG content height as number")))?;
 
     let qr_path = qr_svg.get_child("path")
-        .expect("QR code does not have path element")
+        .ok_or_else(|| BysqrError::Render(String::from("QR code does not have path element")))?
         .attributes.get("d")
-        .expect("unable to find d attribute within QR code");
+        .ok_or_else(|| BysqrError::Render(String::from("unable to find d attribute within QR code")))?;
 
     let translate_x = (CONTAINER_WIDTH / 2.0) - (qr_width / 2.0);
     let translate_y = (CONTAINER_WIDTH / 2.0) - (qr_height / 2.0);
@@ -141,6 +144,7 @@ fn insert_qr_content(svg: &mut Element, qr: &str) {
         ("transform".to_string(), format!("translate({},{})", translate_x, translate_y)),
     ]);
     svg.children.push(xmltree::XMLNode::Element(path));
+    Ok(())
 }
 
 fn create_empty_svg() -> Element {
@@ -154,8 +158,13 @@ fn create_empty_svg() -> Element {
     final_svg
 }
 
-pub fn create_pay_svg(content: &str, theme: Theme) -> Vec<u8> {
-    let code = QrCode::new(content.as_bytes()).expect("unable to create QR code");
+/// Create the QR code of the content, with the smallest version and default error correction level.
+pub fn create_code(content: &str) -> Result<QrCode, BysqrError> {
+    QrCode::new(content.as_bytes()).map_err(|e| BysqrError::Qr(e.to_string()))
+}
+
+pub fn create_pay_svg(content: &str, theme: Theme) -> Result<Vec<u8>, BysqrError> {
+    let code = create_code(content)?;
 
     let qr_size = (CONTAINER_WIDTH - 12.0) as u32;
 
@@ -166,7 +175,7 @@ pub fn create_pay_svg(content: &str, theme: Theme) -> Vec<u8> {
 
     let mut svg = create_empty_svg();
     insert_background(&mut svg, &theme.background_color);
-    insert_qr_content(&mut svg, &svg_image);
+    insert_qr_content(&mut svg, &svg_image)?;
     insert_outline(&mut svg, &theme.outline_color);
     insert_pay_icon(&mut svg, &theme.icon_color);
     insert_by_square_text(&mut svg, &theme.by_square_text_color);
@@ -175,41 +184,43 @@ pub fn create_pay_svg(content: &str, theme: Theme) -> Vec<u8> {
     let mut qr = Vec::new();
     let emitter_options = EmitterConfig::default()
         .write_document_declaration(false);
-    svg.write_with_config(&mut qr, emitter_options).expect("unable to write generated SVG. possible XML corruption");
-    qr
+    svg.write_with_config(&mut qr, emitter_options)
+        .map_err(|e| BysqrError::Render(format!("unable to write generated SVG. possible XML corruption: {}", e)))?;
+    Ok(qr)
 }
 
-pub fn map_svg(svg: &Vec<u8>, size: u32) -> Pixmap {
-    let svg_tree = Tree::from_data(&svg, &Options::default()).unwrap();
+pub fn map_svg(svg: &[u8], size: u32) -> Result<Pixmap, BysqrError> {
+    let svg_tree = Tree::from_data(svg, &Options::default()).map_err(|e| BysqrError::Render(e.to_string()))?;
 
     let scale: f32 = size as f32 / CONTAINER_WIDTH;
 
     let width: u32 = size;
     let height: u32 = (CONTAINER_HEIGHT * scale) as u32;
 
-    let mut pixmap = Pixmap::new(width, height).expect("unable to create pixmap");
+    let mut pixmap = Pixmap::new(width, height)
+        .ok_or_else(|| BysqrError::Render(format!("unable to create pixmap of size {}x{}", width, height)))?;
     resvg::render(
         &svg_tree,
         Transform::from_scale(scale, scale),
         &mut pixmap.as_mut()
     );
-    pixmap
+    Ok(pixmap)
 }
 
-pub fn render_png(svg: &Vec<u8>, size: u32) -> Vec<u8> {
-    let pixmap = map_svg(svg, size);
+pub fn render_png(svg: &[u8], size: u32) -> Result<Vec<u8>, BysqrError> {
+    let pixmap = map_svg(svg, size)?;
 
-    pixmap.encode_png().expect("unable to save image")
+    pixmap.encode_png().map_err(|e| BysqrError::Render(e.to_string()))
 }
 
-pub fn to_base64_png(svg: &Vec<u8>, size: u32) -> String {
-    let buf = render_png(svg, size);
+pub fn to_base64_png(svg: &[u8], size: u32) -> Result<String, BysqrError> {
+    let buf = render_png(svg, size)?;
     let base64_content = base64::engine::general_purpose::STANDARD.encode(&buf);
-    format!("data:image/png;base64,{}", base64_content)
+    Ok(format!("data:image/png;base64,{}", base64_content))
 }
 
-pub fn render_jpeg(svg: &Vec<u8>, size: u32, quality: u8) -> Vec<u8> {
-    let pixmap = map_svg(svg, size);
+pub fn render_jpeg(svg: &[u8], size: u32, quality: u8) -> Result<Vec<u8>, BysqrError> {
+    let pixmap = map_svg(svg, size)?;
     let (width, height) = (pixmap.width(), pixmap.height());
     let mut buf = Vec::with_capacity((width * height * 3) as usize);
 
@@ -221,12 +232,12 @@ pub fn render_jpeg(svg: &Vec<u8>, size: u32, quality: u8) -> Vec<u8> {
 
     let mut jpeg_buffer = Vec::new();
     let encoder = Encoder::new(&mut jpeg_buffer, quality);
-    encoder.encode(&buf, width as u16, height as u16, ColorType::Rgb).ok().unwrap();
-    jpeg_buffer
+    encoder.encode(&buf, width as u16, height as u16, ColorType::Rgb).map_err(|e| BysqrError::Render(e.to_string()))?;
+    Ok(jpeg_buffer)
 }
 
-pub fn to_base64_jpeg(svg: &Vec<u8>, size: u32, quality: u8) -> String {
-    let buf = render_jpeg(svg, size, quality);
+pub fn to_base64_jpeg(svg: &[u8], size: u32, quality: u8) -> Result<String, BysqrError> {
+    let buf = render_jpeg(svg, size, quality)?;
     let content = base64::engine::general_purpose::STANDARD.encode(&buf);
-    format!("data:image/jpeg;base64,{}", content)
+    Ok(format!("data:image/jpeg;base64,{}", content))
 }
@@ -0,0 +1,1162 @@
+//! Reader of QR codes in raster images, such as screenshots, photos or scans of Pay by Square codes.
+//!
+//! The image is binarized, the three finder patterns are located and the grid of modules is sampled
+//! through a perspective transform, using the bottom right alignment pattern when the version has one.
+//! Codewords are corrected with Reed-Solomon and the numeric, alphanumeric and byte segments are decoded.
+
+use image::{DynamicImage, GrayImage};
+
+use crate::decoder;
+use crate::error::BysqrError;
+use crate::models::Pay;
+
+/// Error correction codewords per block, indexed by error correction level (L, M, Q, H) and version.
+const ECC_CODEWORDS_PER_BLOCK: [[u8; 41]; 4] = [
+    [0, 7, 10, 15, 20, 26, 18, 20, 24, 30, 18, 20, 24, 26, 30, 22, 24, 28, 30, 28, 28, 28, 28, 30, 30, 26, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+    [0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28],
+    [0, 13, 22, 18, 26, 18, 24, 18, 22, 20, 24, 28, 26, 24, 20, 30, 24, 28, 28, 26, 30, 28, 30, 30, 30, 30, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+    [0, 17, 28, 22, 16, 22, 28, 26, 26, 24, 28, 24, 28, 22, 24, 24, 30, 28, 28, 26, 28, 30, 24, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+];
+
+/// Number of error correction blocks, indexed by error correction level (L, M, Q, H) and version.
+const NUM_ERROR_CORRECTION_BLOCKS: [[u8; 41]; 4] = [
+    [0, 1, 1, 1, 1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 4, 6, 6, 6, 6, 7, 8, 8, 9, 9, 10, 12, 12, 12, 13, 14, 15, 16, 17, 18, 19, 19, 20, 21, 22, 24, 25],
+    [0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21, 23, 25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49],
+    [0, 1, 1, 2, 2, 4, 4, 6, 6, 8, 8, 8, 10, 12, 16, 12, 17, 16, 18, 21, 20, 23, 23, 25, 27, 29, 34, 34, 35, 38, 40, 43, 45, 48, 51, 53, 56, 59, 62, 65, 68],
+    [0, 1, 1, 2, 4, 4, 4, 5, 6, 8, 8, 11, 11, 16, 16, 18, 16, 19, 21, 25, 25, 25, 34, 30, 32, 35, 37, 40, 42, 45, 48, 51, 54, 57, 60, 63, 66, 70, 74, 77, 81],
+];
+
+const ALPHANUMERIC: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+fn scan_error(reason: &str) -> BysqrError {
+    BysqrError::Scan(reason.to_string())
+}
+
+/// Binarized image, true for dark pixels.
+struct BitImage {
+    width: usize,
+    height: usize,
+    bits: Vec<bool>,
+}
+
+impl BitImage {
+    fn dark(&self, x: i64, y: i64) -> Option<bool> {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            None
+        } else {
+            Some(self.bits[y as usize * self.width + x as usize])
+        }
+    }
+
+    /// Pixel at the point, where pixel (x, y) covers the area from x to x + 1 and y to y + 1.
+    fn dark_at(&self, point: Point) -> Option<bool> {
+        self.dark(point.x.floor() as i64, point.y.floor() as i64)
+    }
+}
+
+/// Threshold between dark and light pixels which minimizes the variance within both groups (Otsu's method).
+fn binarize_global(gray: &GrayImage) -> BitImage {
+    let mut histogram = [0u64; 256];
+    for pixel in gray.pixels() {
+        histogram[pixel.0[0] as usize] += 1;
+    }
+
+    let total: u64 = histogram.iter().sum();
+    let sum: u64 = histogram.iter().enumerate().map(|(value, count)| value as u64 * count).sum();
+
+    let (mut weight, mut weighted_sum) = (0u64, 0u64);
+    let (mut threshold, mut best_variance) = (128u8, 0f64);
+
+    for (value, count) in histogram.iter().enumerate() {
+        weight += count;
+        weighted_sum += value as u64 * count;
+
+        if weight == 0 || weight == total {
+            continue;
+        }
+
+        let mean_dark = weighted_sum as f64 / weight as f64;
+        let mean_light = (sum - weighted_sum) as f64 / (total - weight) as f64;
+        let variance = weight as f64 * (total - weight) as f64 * (mean_dark - mean_light).powi(2);
+
+        if variance > best_variance {
+            best_variance = variance;
+            threshold = value as u8;
+        }
+    }
+
+    BitImage {
+        width: gray.width() as usize,
+        height: gray.height() as usize,
+        bits: gray.pixels().map(|pixel| pixel.0[0] <= threshold).collect(),
+    }
+}
+
+/// Pixels darker than the mean of their neighbourhood are dark, which copes with uneven lighting of photos and scans.
+fn binarize_local(gray: &GrayImage) -> BitImage {
+    let (width, height) = (gray.width() as usize, gray.height() as usize);
+    let radius = (width.min(height) / 16).max(8);
+
+    // Summed-area table with an extra leading row and column of zeros
+    let mut integral = vec![0u64; (width + 1) * (height + 1)];
+    for y in 0..height {
+        let mut row_sum = 0u64;
+        for x in 0..width {
+            row_sum += gray.get_pixel(x as u32, y as u32).0[0] as u64;
+            integral[(y + 1) * (width + 1) + x + 1] = integral[y * (width + 1) + x + 1] + row_sum;
+        }
+    }
+
+    let mut bits = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let (top, bottom) = (y.saturating_sub(radius), (y + radius + 1).min(height));
+
+        for x in 0..width {
+            let (left, right) = (x.saturating_sub(radius), (x + radius + 1).min(width));
+            let area = ((bottom - top) * (right - left)) as u64;
+            let sum = integral[bottom * (width + 1) + right] + integral[top * (width + 1) + left]
+                - integral[top * (width + 1) + right]
+                - integral[bottom * (width + 1) + left];
+
+            let value = gray.get_pixel(x as u32, y as u32).0[0] as u64;
+            bits.push(value * area * 100 < sum * 90);
+        }
+    }
+
+    BitImage { width, height, bits }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+impl Point {
+    fn distance(&self, other: Point) -> f64 {
+        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2)).sqrt()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FinderPattern {
+    center: Point,
+    module_size: f64,
+    /// Number of scanned rows which found the pattern.
+    count: u32,
+}
+
+/// Check the runs of dark, light, dark, light and dark pixels have ratio 1:1:3:1:1.
+fn is_finder_ratio(runs: &[usize; 5]) -> bool {
+    let total: usize = runs.iter().sum();
+    if total < 7 {
+        return false;
+    }
+
+    let module = total as f64 / 7.0;
+    let variance = module / 2.0;
+
+    runs.iter()
+        .zip([1.0, 1.0, 3.0, 1.0, 1.0])
+        .all(|(run, modules)| (*run as f64 - module * modules).abs() < variance * modules)
+}
+
+/// Length of the run of pixels with the expected color, starting at `from` and moving in the direction.
+/// The run ends at the border of the image, or fails when it is longer than `max_run`.
+fn run_length(dark: &impl Fn(i64) -> Option<bool>, from: i64, direction: i64, expected: bool, max_run: usize) -> Option<usize> {
+    let mut length = 0;
+
+    while dark(from + direction * length as i64) == Some(expected) {
+        length += 1;
+        if length > max_run {
+            return None;
+        }
+    }
+
+    Some(length)
+}
+
+/// Count the finder pattern runs along the line through the origin, which has to be in the center run.
+/// Returns the center of the pattern as offset from the origin pixel, with total length of the runs.
+fn cross_check(dark: impl Fn(i64) -> Option<bool>, max_run: usize) -> Option<(f64, usize)> {
+    if dark(0) != Some(true) {
+        return None;
+    }
+
+    let center_before = run_length(&dark, 0, -1, true, max_run)?;
+    let light_before = run_length(&dark, -(center_before as i64), -1, false, max_run)?;
+    let dark_before = run_length(&dark, -((center_before + light_before) as i64), -1, true, max_run)?;
+
+    let center_after = run_length(&dark, 1, 1, true, max_run)?;
+    let light_after = run_length(&dark, 1 + center_after as i64, 1, false, max_run)?;
+    let dark_after = run_length(&dark, 1 + (center_after + light_after) as i64, 1, true, max_run)?;
+
+    let runs = [dark_before, light_before, center_before + center_after, light_after, dark_after];
+    if !is_finder_ratio(&runs) {
+        return None;
+    }
+
+    // The center run spans from the first pixel before the origin to the last pixel after it
+    let start = 1 - center_before as i64;
+    let end = 1 + center_after as i64;
+
+    Some(((start + end) as f64 / 2.0, runs.iter().sum()))
+}
+
+fn add_finder_pattern(patterns: &mut Vec<FinderPattern>, center: Point, module_size: f64) {
+    let existing = patterns.iter_mut().find(|pattern| {
+        (pattern.center.x - center.x).abs() <= pattern.module_size
+            && (pattern.center.y - center.y).abs() <= pattern.module_size
+            && (pattern.module_size - module_size).abs() <= pattern.module_size.max(1.0)
+    });
+
+    match existing {
+        Some(pattern) => {
+            let count = pattern.count as f64;
+            pattern.center.x = (pattern.center.x * count + center.x) / (count + 1.0);
+            pattern.center.y = (pattern.center.y * count + center.y) / (count + 1.0);
+            pattern.module_size = (pattern.module_size * count + module_size) / (count + 1.0);
+            pattern.count += 1;
+        }
+        None => patterns.push(FinderPattern { center, module_size, count: 1 }),
+    }
+}
+
+/// Find centers of the finder patterns by scanning rows for runs with 1:1:3:1:1 ratio,
+/// which are confirmed by the column through the center and then by the row through the center.
+fn find_finder_patterns(image: &BitImage) -> Vec<FinderPattern> {
+    let mut patterns: Vec<FinderPattern> = Vec::new();
+    let step = (image.height / 400).max(1);
+
+    for y in (0..image.height).step_by(step) {
+        // Runs of the row as (start, length), alternating colors starting with the color of the first pixel
+        let row = &image.bits[y * image.width..(y + 1) * image.width];
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        let mut start = 0;
+        for x in 1..=row.len() {
+            if x == row.len() || row[x] != row[start] {
+                runs.push((start, x - start));
+                start = x;
+            }
+        }
+
+        let first_dark = if row.first() == Some(&true) { 0 } else { 1 };
+        for index in (first_dark..runs.len().saturating_sub(4)).step_by(2) {
+            let lengths = [runs[index].1, runs[index + 1].1, runs[index + 2].1, runs[index + 3].1, runs[index + 4].1];
+            if !is_finder_ratio(&lengths) {
+                continue;
+            }
+
+            let total: usize = lengths.iter().sum();
+            let column = (runs[index + 2].0 + runs[index + 2].1 / 2) as i64;
+
+            let vertical = cross_check(|i| image.dark(column, y as i64 + i), total);
+            let Some((offset_y, total_y)) = vertical else {
+                continue;
+            };
+            if (total_y as f64 - total as f64).abs() * 5.0 >= total as f64 * 2.0 {
+                continue;
+            }
+
+            let center_y = y as f64 + offset_y;
+            let horizontal = cross_check(|i| image.dark(column + i, center_y as i64), total);
+            let Some((offset_x, total_x)) = horizontal else {
+                continue;
+            };
+            if (total_x as f64 - total as f64).abs() * 5.0 >= total as f64 * 2.0 {
+                continue;
+            }
+
+            let center = Point { x: column as f64 + offset_x, y: center_y };
+            add_finder_pattern(&mut patterns, center, (total_x + total_y) as f64 / 14.0);
+        }
+    }
+
+    patterns
+}
+
+/// Triples of finder patterns ordered as top left, top right and bottom left, best matching the shape
+/// of a QR code first. The top left pattern is in the right angle of an isosceles triangle.
+fn finder_triples(patterns: &[FinderPattern]) -> Vec<[FinderPattern; 3]> {
+    let mut candidates: Vec<FinderPattern> = patterns.to_vec();
+    candidates.sort_by_key(|pattern| std::cmp::Reverse(pattern.count));
+    candidates.truncate(12);
+
+    let mut triples: Vec<(f64, [FinderPattern; 3])> = Vec::new();
+
+    for i in 0..candidates.len() {
+        for j in i + 1..candidates.len() {
+            for k in j + 1..candidates.len() {
+                let triple = [candidates[i], candidates[j], candidates[k]];
+
+                let sizes = triple.map(|pattern| pattern.module_size);
+                let (min_size, max_size) = (sizes.iter().cloned().fold(f64::MAX, f64::min), sizes.iter().cloned().fold(0.0, f64::max));
+                if max_size > min_size * 1.5 {
+                    continue;
+                }
+
+                // The corner is opposite to the longest side
+                let corner = (0..3)
+                    .max_by(|a, b| {
+                        let side = |c: usize| triple[(c + 1) % 3].center.distance(triple[(c + 2) % 3].center);
+                        side(*a).total_cmp(&side(*b))
+                    })
+                    .unwrap();
+
+                let top_left = triple[corner];
+                let (mut top_right, mut bottom_left) = (triple[(corner + 1) % 3], triple[(corner + 2) % 3]);
+
+                let (a, b) = (top_left.center.distance(top_right.center), top_left.center.distance(bottom_left.center));
+                let c = top_right.center.distance(bottom_left.center);
+
+                if a < min_size * 12.0 || b < min_size * 12.0 {
+                    continue;
+                }
+
+                let penalty = (a - b).abs() / a.max(b) + (a * a + b * b - c * c).abs() / (c * c);
+                if penalty > 0.5 {
+                    continue;
+                }
+
+                // Image coordinates grow downwards, so the cross product is positive for the upright code
+                let cross = (top_right.center.x - top_left.center.x) * (bottom_left.center.y - top_left.center.y)
+                    - (top_right.center.y - top_left.center.y) * (bottom_left.center.x - top_left.center.x);
+                if cross < 0.0 {
+                    std::mem::swap(&mut top_right, &mut bottom_left);
+                }
+
+                triples.push((penalty, [top_left, top_right, bottom_left]));
+            }
+        }
+    }
+
+    triples.sort_by(|a, b| a.0.total_cmp(&b.0));
+    triples.into_iter().map(|(_, triple)| triple).take(4).collect()
+}
+
+/// Projective transform of module coordinates to image coordinates.
+struct Transform([f64; 8]);
+
+impl Transform {
+    /// Solve the transform mapping each of the four source points to the destination point.
+    fn from_points(source: [Point; 4], destination: [Point; 4]) -> Option<Transform> {
+        let mut matrix = [[0f64; 9]; 8];
+
+        for (index, (s, d)) in source.iter().zip(destination.iter()).enumerate() {
+            matrix[index * 2] = [s.x, s.y, 1.0, 0.0, 0.0, 0.0, -s.x * d.x, -s.y * d.x, d.x];
+            matrix[index * 2 + 1] = [0.0, 0.0, 0.0, s.x, s.y, 1.0, -s.x * d.y, -s.y * d.y, d.y];
+        }
+
+        // Gaussian elimination with partial pivoting
+        for column in 0..8 {
+            let pivot = (column..8).max_by(|a, b| matrix[*a][column].abs().total_cmp(&matrix[*b][column].abs()))?;
+            if matrix[pivot][column].abs() < 1e-12 {
+                return None;
+            }
+            matrix.swap(column, pivot);
+
+            let pivot_row = matrix[column];
+            for (index, row) in matrix.iter_mut().enumerate() {
+                if index != column {
+                    let factor = row[column] / pivot_row[column];
+                    for (value, pivot_value) in row.iter_mut().zip(pivot_row.iter()).skip(column) {
+                        *value -= factor * pivot_value;
+                    }
+                }
+            }
+        }
+
+        let mut coefficients = [0f64; 8];
+        for (index, coefficient) in coefficients.iter_mut().enumerate() {
+            *coefficient = matrix[index][8] / matrix[index][index];
+        }
+
+        Some(Transform(coefficients))
+    }
+
+    fn apply(&self, x: f64, y: f64) -> Point {
+        let h = &self.0;
+        let denominator = h[6] * x + h[7] * y + 1.0;
+
+        Point { x: (h[0] * x + h[1] * y + h[2]) / denominator, y: (h[3] * x + h[4] * y + h[5]) / denominator }
+    }
+}
+
+/// Find the center of the bottom right alignment pattern near its estimated position, by matching
+/// the 5x5 pattern of modules. Returns the centroid of the best matching positions.
+fn find_alignment_pattern(image: &BitImage, finders: &[FinderPattern; 3], dimension: usize) -> Option<Point> {
+    let [top_left, top_right, bottom_left] = finders.map(|pattern| pattern.center);
+    let span = (dimension - 7) as f64;
+
+    // Module vectors of the affine approximation, pointing right and down
+    let right = Point { x: (top_right.x - top_left.x) / span, y: (top_right.y - top_left.y) / span };
+    let down = Point { x: (bottom_left.x - top_left.x) / span, y: (bottom_left.y - top_left.y) / span };
+
+    let modules = dimension as f64 - 10.0;
+    let estimate = Point {
+        x: top_left.x + (right.x + down.x) * modules,
+        y: top_left.y + (right.y + down.y) * modules,
+    };
+
+    let module_size = (right.x.hypot(right.y) + down.x.hypot(down.y)) / 2.0;
+    let radius = (module_size * 5.0).ceil() as i64;
+
+    let (mut best_score, mut matches) = (0, Vec::new());
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            let center = Point { x: estimate.x.floor() + dx as f64 + 0.5, y: estimate.y.floor() + dy as f64 + 0.5 };
+
+            let mut score = 0;
+            for j in -2i32..=2 {
+                for i in -2i32..=2 {
+                    let expected = i.abs().max(j.abs()) != 1;
+                    let point = Point {
+                        x: center.x + right.x * i as f64 + down.x * j as f64,
+                        y: center.y + right.y * i as f64 + down.y * j as f64,
+                    };
+
+                    if image.dark_at(point) == Some(expected) {
+                        score += 1;
+                    }
+                }
+            }
+
+            if score > best_score {
+                best_score = score;
+                matches.clear();
+            }
+            if score == best_score {
+                matches.push(center);
+            }
+        }
+    }
+
+    if best_score < 23 {
+        return None;
+    }
+
+    let count = matches.len() as f64;
+    Some(Point {
+        x: matches.iter().map(|point| point.x).sum::<f64>() / count,
+        y: matches.iter().map(|point| point.y).sum::<f64>() / count,
+    })
+}
+
+/// Sample the modules of the code with the given dimension, true for dark modules.
+fn sample_grid(image: &BitImage, finders: &[FinderPattern; 3], dimension: usize) -> Option<Vec<Vec<bool>>> {
+    let [top_left, top_right, bottom_left] = finders.map(|pattern| pattern.center);
+    let size = dimension as f64;
+
+    let corner = if dimension > 21 { find_alignment_pattern(image, finders, dimension) } else { None };
+    let (source, destination) = match corner {
+        Some(alignment) => (Point { x: size - 6.5, y: size - 6.5 }, alignment),
+        None => (
+            Point { x: size - 3.5, y: size - 3.5 },
+            Point { x: top_right.x + bottom_left.x - top_left.x, y: top_right.y + bottom_left.y - top_left.y },
+        ),
+    };
+
+    let transform = Transform::from_points(
+        [Point { x: 3.5, y: 3.5 }, Point { x: size - 3.5, y: 3.5 }, Point { x: 3.5, y: size - 3.5 }, source],
+        [top_left, top_right, bottom_left, destination],
+    )?;
+
+    (0..dimension)
+        .map(|y| {
+            (0..dimension)
+                .map(|x| image.dark_at(transform.apply(x as f64 + 0.5, y as f64 + 0.5)))
+                .collect::<Option<Vec<bool>>>()
+        })
+        .collect()
+}
+
+/// Number of modules available for data and error correction codewords, including remainder bits.
+fn num_raw_data_modules(version: usize) -> usize {
+    let mut result = (16 * version + 128) * version + 64;
+
+    if version >= 2 {
+        let alignment_count = version / 7 + 2;
+        result -= (25 * alignment_count - 10) * alignment_count - 55;
+
+        if version >= 7 {
+            result -= 36;
+        }
+    }
+
+    result
+}
+
+fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+
+    let count = version / 7 + 2;
+    let step = if version == 32 { 26 } else { (version * 4 + count * 2 + 1) / (count * 2 - 2) * 2 };
+
+    let mut positions = vec![6];
+    for index in 0..count - 1 {
+        positions.insert(1, version * 4 + 10 - index * step);
+    }
+
+    positions
+}
+
+/// Modules of finder, separator, timing and alignment patterns, format and version information.
+fn function_modules(version: usize) -> Vec<Vec<bool>> {
+    let size = version * 4 + 17;
+    let mut function = vec![vec![false; size]; size];
+
+    let mut fill = |left: usize, top: usize, width: usize, height: usize| {
+        for row in function.iter_mut().skip(top).take(height) {
+            for module in row.iter_mut().skip(left).take(width) {
+                *module = true;
+            }
+        }
+    };
+
+    // Finder patterns with separators and format information
+    fill(0, 0, 9, 9);
+    fill(size - 8, 0, 8, 9);
+    fill(0, size - 8, 9, 8);
+
+    // Timing patterns
+    fill(6, 0, 1, size);
+    fill(0, 6, size, 1);
+
+    let positions = alignment_positions(version);
+    let last = positions.len().saturating_sub(1);
+    for (i, x) in positions.iter().enumerate() {
+        for (j, y) in positions.iter().enumerate() {
+            // Corners except the bottom right one overlap with the finder patterns
+            let corner = (i == 0 || i == last) && (j == 0 || j == last);
+            if !corner || (i == last && j == last) {
+                fill(x - 2, y - 2, 5, 5);
+            }
+        }
+    }
+
+    if version >= 7 {
+        fill(size - 11, 0, 3, 6);
+        fill(0, size - 11, 6, 3);
+    }
+
+    function
+}
+
+/// Error correction level as the index into the tables (L, M, Q, H) and the mask, from format information.
+fn read_format(grid: &[Vec<bool>]) -> Option<(usize, usize)> {
+    let size = grid.len();
+    let bit = |x: usize, y: usize| grid[y][x] as u32;
+
+    let (mut first, mut second) = (0u32, 0u32);
+    for i in 0..15 {
+        let (x, y) = match i {
+            0..=5 => (8, i),
+            6 => (8, 7),
+            7 => (8, 8),
+            8 => (7, 8),
+            _ => (14 - i, 8),
+        };
+        first |= bit(x, y) << i;
+
+        let (x, y) = if i < 8 { (size - 1 - i, 8) } else { (8, size - 15 + i) };
+        second |= bit(x, y) << i;
+    }
+
+    let mut best: Option<(u32, usize, usize)> = None;
+    for level_bits in 0..4u32 {
+        for mask in 0..8u32 {
+            let data = level_bits << 3 | mask;
+            let mut remainder = data;
+            for _ in 0..10 {
+                remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+            }
+            let format = (data << 10 | remainder) ^ 0x5412;
+
+            let distance = (format ^ first).count_ones().min((format ^ second).count_ones());
+            if best.is_none_or(|(best_distance, _, _)| distance < best_distance) {
+                // Level bits 01, 00, 11 and 10 stand for L, M, Q and H
+                let level = [1, 0, 3, 2][level_bits as usize];
+                best = Some((distance, level, mask as usize));
+            }
+        }
+    }
+
+    best.filter(|(distance, _, _)| *distance <= 3).map(|(_, level, mask)| (level, mask))
+}
+
+/// Version from the version information of codes with version 7 or higher.
+fn read_version(grid: &[Vec<bool>]) -> Option<usize> {
+    let size = grid.len();
+
+    let (mut first, mut second) = (0u32, 0u32);
+    for i in 0..18 {
+        let (a, b) = (size - 11 + i % 3, i / 3);
+        first |= (grid[b][a] as u32) << i;
+        second |= (grid[a][b] as u32) << i;
+    }
+
+    (7..=40u32)
+        .map(|version| {
+            let mut remainder = version;
+            for _ in 0..12 {
+                remainder = (remainder << 1) ^ ((remainder >> 11) * 0x1F25);
+            }
+            let info = version << 12 | remainder;
+
+            ((info ^ first).count_ones().min((info ^ second).count_ones()), version as usize)
+        })
+        .min()
+        .filter(|(distance, _)| *distance <= 3)
+        .map(|(_, version)| version)
+}
+
+fn is_masked(mask: usize, x: usize, y: usize) -> bool {
+    match mask {
+        0 => (x + y).is_multiple_of(2),
+        1 => y.is_multiple_of(2),
+        2 => x.is_multiple_of(3),
+        3 => (x + y).is_multiple_of(3),
+        4 => (x / 3 + y / 2).is_multiple_of(2),
+        5 => x * y % 2 + x * y % 3 == 0,
+        6 => (x * y % 2 + x * y % 3).is_multiple_of(2),
+        _ => ((x + y) % 2 + x * y % 3).is_multiple_of(2),
+    }
+}
+
+/// Read the codewords in the zigzag order, from the bottom right corner in columns of two modules.
+fn read_codewords(grid: &[Vec<bool>], version: usize, mask: usize) -> Vec<u8> {
+    let size = grid.len();
+    let function = function_modules(version);
+    let count = num_raw_data_modules(version) / 8;
+
+    let mut codewords = vec![0u8; count];
+    let mut index = 0;
+
+    let mut right = size as i64 - 1;
+    while right >= 1 {
+        // The vertical timing pattern is skipped
+        if right == 6 {
+            right = 5;
+        }
+
+        for vertical in 0..size {
+            for j in 0..2 {
+                let x = (right - j) as usize;
+                let upward = (right + 1) & 2 == 0;
+                let y = if upward { size - 1 - vertical } else { vertical };
+
+                if !function[y][x] && index < count * 8 {
+                    if grid[y][x] ^ is_masked(mask, x, y) {
+                        codewords[index / 8] |= 0x80 >> (index % 8);
+                    }
+                    index += 1;
+                }
+            }
+        }
+
+        right -= 2;
+    }
+
+    codewords
+}
+
+/// Arithmetic in GF(256) with the primitive polynomial x^8 + x^4 + x^3 + x^2 + 1 used by QR codes.
+struct Galois {
+    exp: [u8; 512],
+    log: [u8; 256],
+}
+
+impl Galois {
+    fn new() -> Self {
+        let (mut exp, mut log) = ([0u8; 512], [0u8; 256]);
+
+        let mut value = 1u16;
+        for (i, power) in exp.iter_mut().enumerate() {
+            // Powers repeat with period 255, the table is doubled to skip the modulo in multiplication
+            *power = value as u8;
+            if i < 255 {
+                log[value as usize] = i as u8;
+            }
+
+            value <<= 1;
+            if value & 0x100 != 0 {
+                value ^= 0x11D;
+            }
+        }
+
+        Galois { exp, log }
+    }
+
+    fn multiply(&self, a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            0
+        } else {
+            self.exp[self.log[a as usize] as usize + self.log[b as usize] as usize]
+        }
+    }
+
+    fn divide(&self, a: u8, b: u8) -> u8 {
+        if a == 0 {
+            0
+        } else {
+            self.exp[self.log[a as usize] as usize + 255 - self.log[b as usize] as usize]
+        }
+    }
+
+    fn power(&self, exponent: usize) -> u8 {
+        self.exp[exponent % 255]
+    }
+
+    /// Evaluate the polynomial, with coefficients from the lowest degree, at x.
+    fn evaluate(&self, polynomial: &[u8], x: u8) -> u8 {
+        polynomial.iter().rev().fold(0, |result, coefficient| self.multiply(result, x) ^ coefficient)
+    }
+}
+
+/// Correct errors of the block in place, the first codeword is the coefficient of the highest degree.
+/// Returns None when there are more errors than the error correction codewords can correct.
+fn correct_errors(galois: &Galois, block: &mut [u8], ecc_len: usize) -> Option<()> {
+    let n = block.len();
+    let polynomial: Vec<u8> = block.iter().rev().cloned().collect();
+
+    let syndromes: Vec<u8> = (0..ecc_len).map(|i| galois.evaluate(&polynomial, galois.power(i))).collect();
+    if syndromes.iter().all(|syndrome| *syndrome == 0) {
+        return Some(());
+    }
+
+    // Berlekamp-Massey algorithm for the error locator polynomial
+    let (mut locator, mut previous) = (vec![1u8], vec![1u8]);
+    let (mut length, mut shift, mut previous_discrepancy) = (0usize, 1usize, 1u8);
+
+    for step in 0..ecc_len {
+        let mut discrepancy = syndromes[step];
+        for i in 1..=length.min(locator.len() - 1) {
+            discrepancy ^= galois.multiply(locator[i], syndromes[step - i]);
+        }
+
+        if discrepancy == 0 {
+            shift += 1;
+            continue;
+        }
+
+        let factor = galois.divide(discrepancy, previous_discrepancy);
+        let mut updated = locator.clone();
+        if updated.len() < previous.len() + shift {
+            updated.resize(previous.len() + shift, 0);
+        }
+        for (i, coefficient) in previous.iter().enumerate() {
+            updated[i + shift] ^= galois.multiply(factor, *coefficient);
+        }
+
+        if 2 * length <= step {
+            length = step + 1 - length;
+            previous = locator;
+            previous_discrepancy = discrepancy;
+            shift = 1;
+        } else {
+            shift += 1;
+        }
+        locator = updated;
+    }
+
+    while locator.len() > 1 && locator.last() == Some(&0) {
+        locator.pop();
+    }
+    if locator.len() - 1 != length || length * 2 > ecc_len {
+        return None;
+    }
+
+    // Error evaluator polynomial, syndromes times locator modulo x^ecc_len
+    let mut evaluator = vec![0u8; ecc_len];
+    for (i, syndrome) in syndromes.iter().enumerate() {
+        for (j, coefficient) in locator.iter().enumerate() {
+            if i + j < ecc_len {
+                evaluator[i + j] ^= galois.multiply(*syndrome, *coefficient);
+            }
+        }
+    }
+
+    // Formal derivative keeps only terms of odd degree in characteristic 2
+    let derivative: Vec<u8> = locator.iter().enumerate().skip(1).map(|(i, c)| if i % 2 == 1 { *c } else { 0 }).collect();
+
+    let mut corrected = 0;
+    for (position, codeword) in block.iter_mut().enumerate() {
+        // The codeword at the position is the coefficient of x^(n - 1 - position)
+        let locator_value = galois.power(n - 1 - position);
+        let inverse = galois.divide(1, locator_value);
+
+        if galois.evaluate(&locator, inverse) != 0 {
+            continue;
+        }
+
+        let denominator = galois.evaluate(&derivative, inverse);
+        if denominator == 0 {
+            return None;
+        }
+
+        let magnitude = galois.multiply(locator_value, galois.divide(galois.evaluate(&evaluator, inverse), denominator));
+        *codeword ^= magnitude;
+        corrected += 1;
+    }
+
+    if corrected != length {
+        return None;
+    }
+
+    Some(())
+}
+
+/// Split the interleaved codewords into blocks, correct them and join their data codewords.
+fn correct_codewords(codewords: &[u8], version: usize, level: usize) -> Option<Vec<u8>> {
+    let block_count = NUM_ERROR_CORRECTION_BLOCKS[level][version] as usize;
+    let ecc_len = ECC_CODEWORDS_PER_BLOCK[level][version] as usize;
+
+    let short_count = block_count - codewords.len() % block_count;
+    let short_len = codewords.len() / block_count;
+    let short_data_len = short_len - ecc_len;
+
+    // Short blocks have one data codeword less, which is skipped while interleaving
+    let mut blocks: Vec<Vec<u8>> = (0..block_count).map(|_| Vec::with_capacity(short_len + 1)).collect();
+    let mut iter = codewords.iter();
+    for i in 0..=short_len {
+        for (j, block) in blocks.iter_mut().enumerate() {
+            if i == short_data_len && j < short_count {
+                continue;
+            }
+            block.push(*iter.next()?);
+        }
+    }
+
+    let galois = Galois::new();
+    let mut data: Vec<u8> = Vec::new();
+    for mut block in blocks {
+        correct_errors(&galois, &mut block, ecc_len)?;
+        data.extend_from_slice(&block[..block.len() - ecc_len]);
+    }
+
+    Some(data)
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn remaining(&self) -> usize {
+        self.data.len() * 8 - self.position
+    }
+
+    fn read(&mut self, count: usize) -> Result<u32, BysqrError> {
+        if count > self.remaining() {
+            return Err(scan_error("data segment is longer than the code"));
+        }
+
+        let mut value = 0u32;
+        for _ in 0..count {
+            let bit = self.data[self.position / 8] >> (7 - self.position % 8) & 1;
+            value = value << 1 | bit as u32;
+            self.position += 1;
+        }
+
+        Ok(value)
+    }
+}
+
+/// Decode the numeric, alphanumeric and byte segments of the data codewords into text.
+fn decode_segments(data: &[u8], version: usize) -> Result<String, BysqrError> {
+    let mut reader = BitReader { data, position: 0 };
+    let mut content: Vec<u8> = Vec::new();
+
+    // Length of the character count depends on the version range
+    let size_class = match version {
+        1..=9 => 0,
+        10..=26 => 1,
+        _ => 2,
+    };
+
+    while reader.remaining() >= 4 {
+        match reader.read(4)? {
+            0 => break,
+            // Numeric, three digits in 10 bits
+            0b0001 => {
+                let mut count = reader.read([10, 12, 14][size_class])? as usize;
+                while count > 0 {
+                    let digits = count.min(3);
+                    let value = reader.read([4, 7, 10][digits - 1])?;
+                    content.extend_from_slice(format!("{:0width$}", value, width = digits).as_bytes());
+                    count -= digits;
+                }
+            }
+            // Alphanumeric, two characters in 11 bits
+            0b0010 => {
+                let mut count = reader.read([9, 11, 13][size_class])? as usize;
+                while count > 0 {
+                    if count >= 2 {
+                        let value = reader.read(11)? as usize;
+                        content.push(*ALPHANUMERIC.get(value / 45).ok_or_else(|| scan_error("invalid alphanumeric character"))?);
+                        content.push(*ALPHANUMERIC.get(value % 45).ok_or_else(|| scan_error("invalid alphanumeric character"))?);
+                        count -= 2;
+                    } else {
+                        let value = reader.read(6)? as usize;
+                        content.push(*ALPHANUMERIC.get(value).ok_or_else(|| scan_error("invalid alphanumeric character"))?);
+                        count -= 1;
+                    }
+                }
+            }
+            0b0100 => {
+                let count = reader.read([8, 16, 16][size_class])?;
+                for _ in 0..count {
+                    content.push(reader.read(8)? as u8);
+                }
+            }
+            // Extended channel interpretation, the designator is ignored
+            0b0111 => {
+                let first = reader.read(8)?;
+                if first & 0x80 != 0 {
+                    reader.read(if first & 0x40 == 0 { 8 } else { 16 })?;
+                }
+            }
+            // Structured append header
+            0b0011 => {
+                reader.read(16)?;
+            }
+            mode => return Err(scan_error(&format!("unsupported data mode {:04b}", mode))),
+        }
+    }
+
+    String::from_utf8(content).map_err(|_| scan_error("content is not valid UTF-8 text"))
+}
+
+/// Decode the code from the sampled grid, returns the version read from the version information
+/// when it does not match the dimension of the grid.
+fn decode_grid(grid: &[Vec<bool>]) -> Result<String, Option<usize>> {
+    let version = (grid.len() - 17) / 4;
+
+    if version >= 7 {
+        match read_version(grid) {
+            Some(read) if read != version => return Err(Some(read)),
+            _ => {}
+        }
+    }
+
+    let (level, mask) = read_format(grid).ok_or(None)?;
+    let codewords = read_codewords(grid, version, mask);
+    let data = correct_codewords(&codewords, version, level).ok_or(None)?;
+
+    decode_segments(&data, version).map_err(|_| None)
+}
+
+fn estimate_dimension(finders: &[FinderPattern; 3]) -> usize {
+    let [top_left, top_right, bottom_left] = finders;
+
+    // Runs of the finder patterns are measured along rows and columns, in a rotated code
+    // they cross the modules diagonally and are longer by 1 / cos of the angle
+    let angle = (top_right.center.y - top_left.center.y).atan2(top_right.center.x - top_left.center.x);
+    let (sin, cos) = angle.sin_cos();
+    let module_size = finders.iter().map(|pattern| pattern.module_size).sum::<f64>() / 3.0 * cos.abs().max(sin.abs());
+
+    let span = (top_left.center.distance(top_right.center) + top_left.center.distance(bottom_left.center)) / 2.0;
+    let dimension = (span / module_size).round() as usize + 7;
+
+    // Dimension of a QR code is 17 + 4 * version
+    ((dimension + 1) / 4 * 4 + 1).clamp(21, 177)
+}
+
+fn scan_image(image: &BitImage) -> Option<String> {
+    for finders in finder_triples(&find_finder_patterns(image)) {
+        let estimate = estimate_dimension(&finders);
+        let mut dimensions = vec![estimate, estimate + 4, estimate.saturating_sub(4)];
+
+        while let Some(dimension) = dimensions.first().cloned() {
+            dimensions.remove(0);
+            if !(21..=177).contains(&dimension) {
+                continue;
+            }
+
+            let Some(grid) = sample_grid(image, &finders, dimension) else {
+                continue;
+            };
+
+            match decode_grid(&grid) {
+                Ok(content) => return Some(content),
+                // The version information is more reliable than the estimate
+                Err(Some(version)) => dimensions.insert(0, version * 4 + 17),
+                Err(None) => {}
+            }
+        }
+    }
+
+    None
+}
+
+/// Find and decode the QR code in the image, returns its content.
+pub fn scan(image: &DynamicImage) -> Result<String, BysqrError> {
+    let gray = image.to_luma8();
+
+    [binarize_global(&gray), binarize_local(&gray)]
+        .iter()
+        .find_map(scan_image)
+        .ok_or_else(|| scan_error("no readable QR code found in the image"))
+}
+
+/// Find the QR code in the image and decode the Pay by Square document it contains.
+pub fn scan_pay(image: &DynamicImage) -> Result<Pay, BysqrError> {
+    let content = scan(image)?;
+
+    decoder::decode(content.trim())
+}
+
+/// Render pages of the PDF document and find the QR code on them, returns content of the first one found.
+/// Pages are rendered at 300 DPI by the PDFium library, which is loaded from the current directory
+/// or from the system libraries at runtime.
+#[cfg(feature = "pdf")]
+pub fn scan_pdf(document: &[u8]) -> Result<String, BysqrError> {
+    use pdfium_render::prelude::{PdfRenderConfig, Pdfium};
+
+    let bindings = Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path("./"))
+        .or_else(|_| Pdfium::bind_to_system_library())
+        .map_err(|e| scan_error(&format!("unable to load PDFium library: {}", e)))?;
+
+    let pdfium = Pdfium::new(bindings);
+    let document = pdfium
+        .load_pdf_from_byte_slice(document, None)
+        .map_err(|e| scan_error(&format!("unable to read PDF document: {}", e)))?;
+
+    // Points of the page are 1/72 inch
+    let config = PdfRenderConfig::new().scale_page_by_factor(300.0 / 72.0);
+
+    for page in document.pages().iter() {
+        let bitmap = page
+            .render_with_config(&config)
+            .map_err(|e| scan_error(&format!("unable to render PDF page: {}", e)))?;
+
+        if let Ok(content) = scan(&bitmap.as_image()) {
+            return Ok(content);
+        }
+    }
+
+    Err(scan_error("no readable QR code found in the document"))
+}
+
+/// Find the QR code on pages of the PDF document and decode the Pay by Square document it contains.
+#[cfg(feature = "pdf")]
+pub fn scan_pdf_pay(document: &[u8]) -> Result<Pay, BysqrError> {
+    let content = scan_pdf(document)?;
+
+    decoder::decode(content.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use image::imageops::{self, FilterType};
+    use image::{DynamicImage, GrayImage, Luma};
+    use qrcode::{Color, QrCode};
+
+    use super::{scan, scan_pay};
+    use crate::models::try_deserialize_pay;
+    use crate::{encoder, qr};
+
+    const CONTENT: &str = "0005M000B8EL2I2FP2PSOB3ENDMC9PB3SESUJ694JE1139U2J1Q25BF5OQ2D1CB3RFMNVV4J4VVG5IKNTC41O399RIJH2DHL5SIKOUR5JPCOAQDROPLVVVR5VT000";
+
+    /// Draw the code with `scale` pixels per module and a quiet zone of four modules.
+    fn render(content: &str, scale: u32) -> GrayImage {
+        let code = QrCode::new(content.as_bytes()).unwrap();
+        let width = code.width() as u32;
+        let colors = code.to_colors();
+        let size = (width + 8) * scale;
+
+        GrayImage::from_fn(size, size, |x, y| {
+            let (module_x, module_y) = ((x / scale).wrapping_sub(4), (y / scale).wrapping_sub(4));
+
+            if module_x < width && module_y < width && colors[(module_y * width + module_x) as usize] == Color::Dark {
+                Luma([0])
+            } else {
+                Luma([255])
+            }
+        })
+    }
+
+    /// Rotate the image around its center by the angle, on a larger white canvas.
+    fn rotate(image: &GrayImage, degrees: f64) -> GrayImage {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let size = image.width() * 3 / 2;
+        let (center, source_center) = (size as f64 / 2.0, image.width() as f64 / 2.0);
+
+        GrayImage::from_fn(size, size, |x, y| {
+            let (dx, dy) = (x as f64 - center, y as f64 - center);
+            let source_x = (cos * dx + sin * dy + source_center).round();
+            let source_y = (-sin * dx + cos * dy + source_center).round();
+
+            if source_x < 0.0 || source_y < 0.0 {
+                return Luma([255]);
+            }
+
+            *image.get_pixel_checked(source_x as u32, source_y as u32).unwrap_or(&Luma([255]))
+        })
+    }
+
+    /// Pseudo-random sequence, so the noise is the same on every run.
+    fn random(seed: &mut u64) -> u32 {
+        *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (*seed >> 33) as u32
+    }
+
+    #[test]
+    fn scans_rendered_pay() {
+        let pay = try_deserialize_pay(include_str!("../example/payment.xml")).unwrap();
+        let svg = qr::create_pay_svg(&encoder::encode(&pay).unwrap(), qr::Theme::default()).unwrap();
+        let image = image::load_from_memory(&qr::render_png(&svg, 400).unwrap()).unwrap();
+
+        assert_eq!(scan_pay(&image).unwrap(), pay);
+    }
+
+    #[test]
+    fn scans_rotated_code() {
+        let image = render(CONTENT, 4);
+
+        for rotated in [imageops::rotate90(&image), imageops::rotate180(&image), imageops::rotate270(&image)] {
+            assert_eq!(scan(&DynamicImage::ImageLuma8(rotated)).unwrap(), CONTENT);
+        }
+
+        for degrees in [7.0, 30.0, 45.0, -20.0] {
+            assert_eq!(scan(&DynamicImage::ImageLuma8(rotate(&image, degrees))).unwrap(), CONTENT, "rotated by {}", degrees);
+        }
+    }
+
+    #[test]
+    fn scans_scaled_code() {
+        let image = render(CONTENT, 4);
+
+        for size in [image.width() * 3 / 4, image.width() * 3 / 2, image.width() * 5 / 2] {
+            let scaled = imageops::resize(&image, size, size, FilterType::Triangle);
+
+            assert_eq!(scan(&DynamicImage::ImageLuma8(scaled)).unwrap(), CONTENT, "scaled to {}", size);
+        }
+    }
+
+    #[test]
+    fn scans_noisy_code() {
+        let mut image = render(CONTENT, 4);
+        let mut seed = 1;
+
+        // Gray noise over every pixel, with a few of them inverted
+        for pixel in image.pixels_mut() {
+            let noise = (random(&mut seed) % 81) as i32 - 40;
+            let value = if random(&mut seed).is_multiple_of(100) { 255 - pixel.0[0] } else { pixel.0[0] };
+
+            pixel.0[0] = (value as i32 + noise).clamp(0, 255) as u8;
+        }
+
+        assert_eq!(scan(&DynamicImage::ImageLuma8(image)).unwrap(), CONTENT);
+    }
+
+    #[test]
+    fn fails_without_code() {
+        let image = GrayImage::from_pixel(200, 200, Luma([255]));
+
+        assert!(scan(&DynamicImage::ImageLuma8(image)).is_err());
+    }
+}
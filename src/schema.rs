@@ -0,0 +1,294 @@
+use xml::attribute::OwnedAttribute;
+use xml::common::Position;
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::error::BysqrError;
+
+/// Namespace of the XML Schema instance attributes, such as xsi:schemaLocation, which are always allowed.
+const XSI_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema-instance";
+
+/// Element of the document structure with the number of its occurrences within the parent element.
+struct Element {
+    name: &'static str,
+    min_occurs: usize,
+    /// True when maxOccurs is unbounded, otherwise the element can occur only once.
+    unbounded: bool,
+    /// Allowed child elements, the element contains only text when empty.
+    children: &'static [Element],
+}
+
+const fn required(name: &'static str) -> Element {
+    Element { name, min_occurs: 1, unbounded: false, children: &[] }
+}
+
+const fn optional(name: &'static str) -> Element {
+    Element { name, min_occurs: 0, unbounded: false, children: &[] }
+}
+
+const BANK_ACCOUNT: [Element; 2] = [required("IBAN"), optional("BIC")];
+
+const STANDING_ORDER_EXT: [Element; 4] = [optional("Day"), optional("Month"), required("Periodicity"), optional("LastDate")];
+
+const DIRECT_DEBIT_EXT: [Element; 10] = [
+    required("DirectDebitScheme"),
+    required("DirectDebitType"),
+    optional("VariableSymbol"),
+    optional("SpecificSymbol"),
+    optional("OriginatorsReferenceInformation"),
+    optional("MandateID"),
+    optional("CreditorID"),
+    optional("ContractID"),
+    optional("MaxAmount"),
+    optional("ValidTillDate"),
+];
+
+const PAYMENT: [Element; 15] = [
+    required("PaymentOptions"),
+    optional("Amount"),
+    required("CurrencyCode"),
+    optional("PaymentDueDate"),
+    optional("VariableSymbol"),
+    optional("ConstantSymbol"),
+    optional("SpecificSymbol"),
+    optional("OriginatorsReferenceInformation"),
+    optional("PaymentNote"),
+    Element {
+        name: "BankAccounts",
+        min_occurs: 1,
+        unbounded: false,
+        children: &[Element { name: "BankAccount", min_occurs: 1, unbounded: true, children: &BANK_ACCOUNT }],
+    },
+    Element { name: "StandingOrderExt", min_occurs: 0, unbounded: false, children: &STANDING_ORDER_EXT },
+    Element { name: "DirectDebitExt", min_occurs: 0, unbounded: false, children: &DIRECT_DEBIT_EXT },
+    optional("BeneficiaryName"),
+    optional("BeneficiaryAddressLine1"),
+    optional("BeneficiaryAddressLine2"),
+];
+
+const PAY: Element = Element {
+    name: "Pay",
+    min_occurs: 1,
+    unbounded: false,
+    children: &[
+        optional("InvoiceID"),
+        Element {
+            name: "Payments",
+            min_occurs: 1,
+            unbounded: false,
+            children: &[Element { name: "Payment", min_occurs: 1, unbounded: true, children: &PAYMENT }],
+        },
+    ],
+};
+
+/// Element being read, with the number of occurrences of each of its children so far.
+struct Frame {
+    element: &'static Element,
+    occurrences: Vec<usize>,
+}
+
+impl Frame {
+    fn new(element: &'static Element) -> Self {
+        Frame { element, occurrences: vec![0; element.children.len()] }
+    }
+}
+
+fn schema_error(reader: &EventReader<&[u8]>, message: String) -> BysqrError {
+    let position = reader.position();
+
+    BysqrError::Schema { line: position.row + 1, column: position.column + 1, message }
+}
+
+/// Check the root element has the type attribute with the expected value and no other attributes.
+fn check_root_attributes(
+    reader: &EventReader<&[u8]>,
+    root: &Element,
+    attributes: &[OwnedAttribute],
+) -> Result<(), BysqrError> {
+    let mut document_type = None;
+
+    for attribute in attributes {
+        match (attribute.name.local_name.as_str(), attribute.name.namespace.as_deref()) {
+            ("type", None) | ("type", Some(XSI_NAMESPACE)) => document_type = Some(attribute.value.as_str()),
+            (_, Some(XSI_NAMESPACE)) => {}
+            (name, _) => return Err(schema_error(reader, format!("unknown attribute {} of <{}>", name, root.name))),
+        }
+    }
+
+    match document_type {
+        Some(value) if value == root.name => Ok(()),
+        Some(value) => Err(schema_error(
+            reader,
+            format!("attribute type of <{}> must be \"{}\", found \"{}\"", root.name, root.name, value),
+        )),
+        None => Err(schema_error(reader, format!("missing attribute type=\"{}\" of <{}>", root.name, root.name))),
+    }
+}
+
+/// Check the XML document has only elements and attributes of the structure, each of them occurring
+/// the allowed number of times. Returns the first violation with its line and column.
+fn check_structure(content: &str, root: &'static Element) -> Result<(), BysqrError> {
+    let mut reader = EventReader::new(content.as_bytes());
+    let mut stack: Vec<Frame> = Vec::new();
+
+    loop {
+        let event = reader.next().map_err(|e| BysqrError::Schema {
+            line: e.position().row + 1,
+            column: e.position().column + 1,
+            message: e.msg().to_string(),
+        })?;
+
+        match event {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                let element = match stack.last_mut() {
+                    None => {
+                        if name.local_name != root.name {
+                            return Err(schema_error(
+                                &reader,
+                                format!("expected root element <{}>, found <{}>", root.name, name.local_name),
+                            ));
+                        }
+
+                        check_root_attributes(&reader, root, &attributes)?;
+                        root
+                    }
+                    Some(parent) => {
+                        let index = parent
+                            .element
+                            .children
+                            .iter()
+                            .position(|child| child.name == name.local_name)
+                            .ok_or_else(|| {
+                                schema_error(&reader, format!("unknown element <{}> in <{}>", name.local_name, parent.element.name))
+                            })?;
+
+                        let child = &parent.element.children[index];
+                        parent.occurrences[index] += 1;
+
+                        if !child.unbounded && parent.occurrences[index] > 1 {
+                            return Err(schema_error(
+                                &reader,
+                                format!("element <{}> can occur only once in <{}>", child.name, parent.element.name),
+                            ));
+                        }
+
+                        if let Some(attribute) = attributes.iter().find(|a| a.name.namespace.as_deref() != Some(XSI_NAMESPACE)) {
+                            return Err(schema_error(
+                                &reader,
+                                format!("unknown attribute {} of <{}>", attribute.name.local_name, child.name),
+                            ));
+                        }
+
+                        child
+                    }
+                };
+
+                stack.push(Frame::new(element));
+            }
+            XmlEvent::EndElement { .. } => {
+                let frame = stack.pop().expect("end of the element which was started");
+
+                for (child, occurrences) in frame.element.children.iter().zip(frame.occurrences) {
+                    if occurrences < child.min_occurs {
+                        return Err(schema_error(
+                            &reader,
+                            format!("missing element <{}> in <{}>", child.name, frame.element.name),
+                        ));
+                    }
+                }
+            }
+            XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                if let Some(frame) = stack.last() {
+                    if !frame.element.children.is_empty() && !text.trim().is_empty() {
+                        return Err(schema_error(&reader, format!("unexpected text in <{}>", frame.element.name)));
+                    }
+                }
+            }
+            XmlEvent::EndDocument => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+/// Check the Pay XML document against the structure defined by spec/bysquare.xsd.
+pub fn check_pay(content: &str) -> Result<(), BysqrError> {
+    check_structure(content, &PAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_pay;
+    use crate::error::BysqrError;
+
+    const ACCOUNT: &str = "            <BankAccount>\n                <IBAN>SK8811000000002945102347</IBAN>\n            </BankAccount>\n";
+
+    /// Pay with one payment, every element on its own line indented by four spaces per level.
+    fn document(root: &str, payments: &str) -> String {
+        format!("<?xml version=\"1.0\"?>\n{}\n<Payments>\n{}</Payments>\n</Pay>\n", root, payments)
+    }
+
+    fn payment(attributes: &str, fields: &str, accounts: &str) -> String {
+        format!(
+            "    <Payment{}>\n        <PaymentOptions>paymentorder</PaymentOptions>\n        <CurrencyCode>EUR</CurrencyCode>\n{}\
+             \x20       <BankAccounts>\n{}        </BankAccounts>\n    </Payment>\n",
+            attributes, fields, accounts,
+        )
+    }
+
+    fn assert_schema_error(content: &str, expected: (u64, u64, &str)) {
+        match check_pay(content) {
+            Err(BysqrError::Schema { line, column, message }) => assert_eq!((line, column, message.as_str()), expected),
+            other => panic!("expected schema error {:?}, got {:?}", expected, other),
+        }
+    }
+
+    #[test]
+    fn accepts_repeated_payments_and_bank_accounts() {
+        let payments = payment("", "", &ACCOUNT.repeat(2)).repeat(2);
+
+        assert!(check_pay(&document("<Pay type=\"Pay\">", &payments)).is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_element() {
+        let content = document("<Pay type=\"Pay\">", &payment("", "        <Note>x</Note>\n", ACCOUNT));
+
+        assert_schema_error(&content, (7, 9, "unknown element <Note> in <Payment>"));
+    }
+
+    #[test]
+    fn rejects_unknown_attribute() {
+        let content = document("<Pay type=\"Pay\">", &payment(" id=\"1\"", "", ACCOUNT));
+        assert_schema_error(&content, (4, 5, "unknown attribute id of <Payment>"));
+
+        let content = document("<Pay type=\"Pay\" version=\"1\">", &payment("", "", ACCOUNT));
+        assert_schema_error(&content, (2, 1, "unknown attribute version of <Pay>"));
+    }
+
+    #[test]
+    fn rejects_missing_or_wrong_type() {
+        let content = document("<Pay>", &payment("", "", ACCOUNT));
+        assert_schema_error(&content, (2, 1, "missing attribute type=\"Pay\" of <Pay>"));
+
+        let content = document("<Pay type=\"Invoice\">", &payment("", "", ACCOUNT));
+        assert_schema_error(&content, (2, 1, "attribute type of <Pay> must be \"Pay\", found \"Invoice\""));
+    }
+
+    #[test]
+    fn rejects_missing_payment_and_bank_account() {
+        assert_schema_error(&document("<Pay type=\"Pay\">", ""), (4, 1, "missing element <Payment> in <Payments>"));
+
+        let content = document("<Pay type=\"Pay\">", &payment("", "", ""));
+        assert_schema_error(&content, (8, 9, "missing element <BankAccount> in <BankAccounts>"));
+    }
+
+    #[test]
+    fn rejects_repeated_bank_accounts_and_iban() {
+        let accounts = "        <BankAccounts>\n".to_string() + ACCOUNT + "        </BankAccounts>\n";
+        let content = document("<Pay type=\"Pay\">", &payment("", &accounts, ACCOUNT));
+        assert_schema_error(&content, (12, 9, "element <BankAccounts> can occur only once in <Payment>"));
+
+        let account = ACCOUNT.replace("</IBAN>\n", "</IBAN>\n                <IBAN>SK8811000000002945102347</IBAN>\n");
+        let content = document("<Pay type=\"Pay\">", &payment("", "", &account));
+        assert_schema_error(&content, (10, 17, "element <IBAN> can occur only once in <BankAccount>"));
+    }
+}
@@ -0,0 +1,603 @@
+use qrcode::{QrCode, Version};
+
+use crate::control::{invoice_fields, invoice_items_fields, Visitor};
+use crate::error::BysqrError;
+use crate::models::{Invoice, InvoiceItems, Pay};
+
+/// Limit the encoded content has to fit into.
+#[derive(Debug, Clone, Copy)]
+pub enum TruncationLimit {
+    /// Maximum number of characters of the encoded string.
+    Length(usize),
+    /// Maximum version (1 - 40) of the QR code, created with the default error correction level.
+    QrVersion(i16),
+}
+
+impl TruncationLimit {
+    pub fn fits(&self, encoded: &str) -> bool {
+        match self {
+            TruncationLimit::Length(length) => encoded.len() <= *length,
+            TruncationLimit::QrVersion(max_version) => match QrCode::new(encoded.as_bytes()) {
+                Ok(code) => match code.version() {
+                    Version::Normal(version) => version <= *max_version,
+                    Version::Micro(_) => true,
+                },
+                Err(_) => false,
+            },
+        }
+    }
+}
+
+/// A field which was shortened in order to fit the encoded content into the limit.
+#[derive(Debug, Clone)]
+pub struct TruncatedField {
+    /// Path to the field, for example "Payments.Payment[0].PaymentNote".
+    pub path: String,
+    pub priority: u16,
+    pub original: String,
+    /// The value after truncation. Empty when the field had to be removed entirely.
+    pub truncated: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TruncationReport {
+    pub fields: Vec<TruncatedField>,
+}
+
+impl TruncationReport {
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Add the truncated field, a field which is already in the report keeps its original value.
+    fn add(&mut self, field: TruncatedField) {
+        match self.fields.iter_mut().find(|reported| reported.path == field.path) {
+            Some(reported) => reported.truncated = field.truncated,
+            None => self.fields.push(field),
+        }
+    }
+}
+
+/// Maximum length of the PaymentNote in Unicode characters, as defined by the XSD.
+pub const PAYMENT_NOTE_MAX_LENGTH: usize = 140;
+
+/// Truncatable fields of Payment with their priority, as defined by bsqr:priority in the XSD.
+/// Fields with priority 999 are never truncated.
+const PAYMENT_FIELDS: [(&str, u16); 5] = [
+    ("PaymentNote", 1),
+    ("ConstantSymbol", 5),
+    ("SpecificSymbol", 6),
+    ("VariableSymbol", 7),
+    ("OriginatorsReferenceInformation", 12),
+];
+
+/// Truncatable fields of DirectDebitExt with their priority.
+const DIRECT_DEBIT_FIELDS: [(&str, u16); 6] = [
+    ("SpecificSymbol", 3),
+    ("VariableSymbol", 4),
+    ("ContractID", 8),
+    ("CreditorID", 9),
+    ("MandateID", 10),
+    ("OriginatorsReferenceInformation", 11),
+];
+
+/// Truncatable text fields of Invoice with their priority. Required fields keep at least one character.
+const INVOICE_FIELDS: [(&str, u16, bool); 24] = [
+    ("InvoiceDescription", 1, false),
+    ("SingleInvoiceLine.ItemName", 2, true),
+    ("SingleInvoiceLine.ItemEANCode", 3, true),
+    ("SingleInvoiceLine.DeliveryNoteLineID", 4, false),
+    ("SingleInvoiceLine.OrderLineID", 5, false),
+    ("SupplierParty.PostalAddress.State", 6, false),
+    ("OrderID", 7, false),
+    ("DeliveryNoteID", 8, false),
+    ("SupplierParty.PostalAddress.PostalZone", 9, true),
+    ("SupplierParty.PostalAddress.CityName", 10, true),
+    ("SupplierParty.PostalAddress.BuildingNumber", 11, false),
+    ("SupplierParty.PostalAddress.StreetName", 12, true),
+    ("SupplierParty.Contact.Name", 13, false),
+    ("SupplierParty.Contact.Telephone", 14, false),
+    ("SupplierParty.Contact.EMail", 15, false),
+    ("CustomerParty.PartyIdentification", 16, false),
+    ("SupplierParty.PartyName", 17, true),
+    ("CustomerParty.PartyName", 17, true),
+    ("SupplierParty.CompanyRegisterID", 18, false),
+    ("CustomerParty.CompanyRegisterID", 18, false),
+    ("SupplierParty.CompanyVATID", 19, false),
+    ("CustomerParty.CompanyVATID", 19, false),
+    ("SupplierParty.CompanyTaxID", 20, false),
+    ("InvoiceID", 21, true),
+];
+
+/// Truncatable text fields of InvoiceItems with their priority, fields of lines are relative to the line.
+const INVOICE_ITEMS_FIELDS: [(&str, u16, bool); 8] = [
+    ("DeliveryNoteReference.DeliveryNoteLineID", 1, false),
+    ("DeliveryNoteReference.DeliveryNoteID", 2, false),
+    ("OrderReference.OrderLineID", 3, false),
+    ("OrderReference.OrderID", 4, false),
+    ("ItemName", 5, true),
+    ("ItemEANCode", 6, true),
+    ("FirstInvoiceLineID", 7, true),
+    ("InvoiceID", 8, true),
+];
+
+/// Field of a document which may be shortened.
+trait TruncatableField<D> {
+    fn path(&self) -> String;
+
+    fn priority(&self) -> u16;
+
+    /// Shortest length the value may be cut to, required fields keep at least one character.
+    fn min_length(&self) -> usize;
+
+    /// Current value of the field, None when the field is not set.
+    fn value(&self, document: &mut D) -> Option<String>;
+
+    fn set_value(&self, document: &mut D, value: Option<String>);
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FieldLocation {
+    Pay,
+    Payment(usize),
+    DirectDebitExt(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Field {
+    location: FieldLocation,
+    name: &'static str,
+    priority: u16,
+}
+
+impl TruncatableField<Pay> for Field {
+    fn path(&self) -> String {
+        match self.location {
+            FieldLocation::Pay => String::from(self.name),
+            FieldLocation::Payment(index) => format!("Payments.Payment[{}].{}", index, self.name),
+            FieldLocation::DirectDebitExt(index) => format!("Payments.Payment[{}].DirectDebitExt.{}", index, self.name),
+        }
+    }
+
+    fn priority(&self) -> u16 {
+        self.priority
+    }
+
+    fn min_length(&self) -> usize {
+        0
+    }
+
+    fn value(&self, pay: &mut Pay) -> Option<String> {
+        match self.location {
+            FieldLocation::Pay => pay.invoice_id.clone(),
+            FieldLocation::Payment(index) => {
+                let payment = pay.payments.payment.get(index)?;
+
+                match self.name {
+                    "PaymentNote" => payment.payment_note.clone(),
+                    "ConstantSymbol" => as_optional_string(&payment.constant_symbol),
+                    "SpecificSymbol" => as_optional_string(&payment.specific_symbol),
+                    "VariableSymbol" => as_optional_string(&payment.variable_symbol),
+                    "OriginatorsReferenceInformation" => payment.originators_reference_information.clone(),
+                    _ => None,
+                }
+            }
+            FieldLocation::DirectDebitExt(index) => {
+                let ext = pay.payments.payment.get(index)?.direct_debit_ext.as_ref()?;
+
+                match self.name {
+                    "SpecificSymbol" => as_optional_string(&ext.specific_symbol),
+                    "VariableSymbol" => as_optional_string(&ext.variable_symbol),
+                    "ContractID" => ext.contract_id.clone(),
+                    "CreditorID" => ext.creditor_id.clone(),
+                    "MandateID" => ext.mandate_id.clone(),
+                    "OriginatorsReferenceInformation" => ext.originators_reference_information.clone(),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Set the value of the field. Prefix of a symbol is a valid symbol too, so parsing does not fail.
+    fn set_value(&self, pay: &mut Pay, value: Option<String>) {
+        match self.location {
+            FieldLocation::Pay => pay.invoice_id = value,
+            FieldLocation::Payment(index) => {
+                let Some(payment) = pay.payments.payment.get_mut(index) else {
+                    return;
+                };
+
+                match self.name {
+                    "PaymentNote" => payment.payment_note = value,
+                    "ConstantSymbol" => payment.constant_symbol = value.and_then(|value| value.parse().ok()),
+                    "SpecificSymbol" => payment.specific_symbol = value.and_then(|value| value.parse().ok()),
+                    "VariableSymbol" => payment.variable_symbol = value.and_then(|value| value.parse().ok()),
+                    "OriginatorsReferenceInformation" => payment.originators_reference_information = value,
+                    _ => {}
+                }
+            }
+            FieldLocation::DirectDebitExt(index) => {
+                let Some(ext) = pay.payments.payment.get_mut(index).and_then(|payment| payment.direct_debit_ext.as_mut()) else {
+                    return;
+                };
+
+                match self.name {
+                    "SpecificSymbol" => ext.specific_symbol = value.and_then(|value| value.parse().ok()),
+                    "VariableSymbol" => ext.variable_symbol = value.and_then(|value| value.parse().ok()),
+                    "ContractID" => ext.contract_id = value,
+                    "CreditorID" => ext.creditor_id = value,
+                    "MandateID" => ext.mandate_id = value,
+                    "OriginatorsReferenceInformation" => ext.originators_reference_information = value,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+fn as_optional_string<T: ToString>(value: &Option<T>) -> Option<String> {
+    value.as_ref().map(|value| value.to_string())
+}
+
+/// List truncatable fields of the Pay, ordered by ascending priority.
+fn truncatable_fields(pay: &Pay) -> Vec<Field> {
+    let mut fields: Vec<Field> = vec![Field { location: FieldLocation::Pay, name: "InvoiceID", priority: 2 }];
+
+    for (index, payment) in pay.payments.payment.iter().enumerate() {
+        for (name, priority) in PAYMENT_FIELDS {
+            fields.push(Field { location: FieldLocation::Payment(index), name, priority });
+        }
+
+        if payment.direct_debit_ext.is_some() {
+            for (name, priority) in DIRECT_DEBIT_FIELDS {
+                fields.push(Field { location: FieldLocation::DirectDebitExt(index), name, priority });
+            }
+        }
+    }
+
+    // Stable sort keeps fields of the same priority in the order of payments
+    fields.sort_by_key(|field| field.priority);
+    fields
+}
+
+/// Text field of Invoice or InvoiceItems, found by its path among the fields visited by `fields`.
+/// A removed optional field is left empty, which is encoded the same way as a missing one.
+struct TextField<D> {
+    path: String,
+    priority: u16,
+    required: bool,
+    fields: fn(&mut D, &mut Visitor),
+}
+
+impl<D> TruncatableField<D> for TextField<D> {
+    fn path(&self) -> String {
+        self.path.clone()
+    }
+
+    fn priority(&self) -> u16 {
+        self.priority
+    }
+
+    fn min_length(&self) -> usize {
+        if self.required { 1 } else { 0 }
+    }
+
+    fn value(&self, document: &mut D) -> Option<String> {
+        let mut found: Option<String> = None;
+
+        (self.fields)(document, &mut |path, value| {
+            if path == self.path && !value.is_empty() {
+                found = Some(value.clone());
+            }
+        });
+
+        found
+    }
+
+    fn set_value(&self, document: &mut D, value: Option<String>) {
+        (self.fields)(document, &mut |path, current| {
+            if path == self.path {
+                *current = value.clone().unwrap_or_default();
+            }
+        });
+    }
+}
+
+/// List text fields of the document with priority found by `priority`, ordered by ascending priority.
+fn text_fields<D>(document: &D, fields: fn(&mut D, &mut Visitor), priority: impl Fn(&str) -> Option<(u16, bool)>) -> Vec<TextField<D>>
+where
+    D: Clone,
+{
+    let mut text_fields: Vec<TextField<D>> = Vec::new();
+
+    fields(&mut document.clone(), &mut |path, _| {
+        if let Some((priority, required)) = priority(&path) {
+            text_fields.push(TextField { path, priority, required, fields });
+        }
+    });
+
+    // Stable sort keeps fields of the same priority in the order of the document
+    text_fields.sort_by_key(|field| field.priority);
+    text_fields
+}
+
+fn set_prefix<D>(document: &mut D, field: &impl TruncatableField<D>, original: &str, length: usize) {
+    let prefix: String = original.chars().take(length).collect();
+
+    field.set_value(document, if prefix.is_empty() { None } else { Some(prefix) });
+}
+
+/// Shorten the fields in the given order, until the encoded content fits into the limit.
+/// Each field is cut only as much as needed, before continuing with the next field.
+fn truncate_fields<D, T, F>(
+    document: D,
+    fields: Vec<T>,
+    limit: TruncationLimit,
+    encode: F,
+    mut report: TruncationReport,
+) -> Result<(D, TruncationReport), BysqrError>
+where
+    T: TruncatableField<D>,
+    F: Fn(&D) -> Result<String, BysqrError>,
+{
+    let mut truncated = document;
+
+    if limit.fits(&encode(&truncated)?) {
+        return Ok((truncated, report));
+    }
+
+    for field in fields {
+        let original = match field.value(&mut truncated) {
+            Some(value) => value,
+            None => continue,
+        };
+
+        let length = original.chars().count();
+        let min_length = field.min_length().min(length);
+
+        set_prefix(&mut truncated, &field, &original, min_length);
+
+        if limit.fits(&encode(&truncated)?) {
+            // Find the longest prefix of the value which still fits
+            let (mut low, mut high) = (min_length, length);
+
+            while low < high {
+                let middle = (low + high).div_ceil(2);
+                set_prefix(&mut truncated, &field, &original, middle);
+
+                if limit.fits(&encode(&truncated)?) {
+                    low = middle;
+                } else {
+                    high = middle - 1;
+                }
+            }
+
+            set_prefix(&mut truncated, &field, &original, low);
+
+            report.add(TruncatedField {
+                path: field.path(),
+                priority: field.priority(),
+                truncated: original.chars().take(low).collect(),
+                original,
+            });
+
+            return Ok((truncated, report));
+        }
+
+        report.add(TruncatedField {
+            path: field.path(),
+            priority: field.priority(),
+            truncated: original.chars().take(min_length).collect(),
+            original,
+        });
+    }
+
+    Err(BysqrError::TruncationLimit)
+}
+
+/// Cut payment notes longer than allowed by the XSD to the maximum length, the shortened notes are added to the report.
+pub(crate) fn truncate_payment_notes(pay: &mut Pay, report: &mut TruncationReport) {
+    for (index, payment) in pay.payments.payment.iter_mut().enumerate() {
+        let Some(note) = &payment.payment_note else {
+            continue;
+        };
+
+        if note.chars().count() > PAYMENT_NOTE_MAX_LENGTH {
+            let truncated: String = note.chars().take(PAYMENT_NOTE_MAX_LENGTH).collect();
+
+            report.add(TruncatedField {
+                path: format!("Payments.Payment[{}].PaymentNote", index),
+                priority: 1,
+                original: note.clone(),
+                truncated: truncated.clone(),
+            });
+
+            payment.payment_note = Some(truncated);
+        }
+    }
+}
+
+/// Shorten fields of the Pay in ascending priority, until the encoded content fits into the limit.
+/// Each field is cut only as much as needed, before continuing with the field of next priority.
+/// Payment notes over the maximum length are cut first, whether the content fits or not.
+/// Fails when the content does not fit even with all truncatable fields removed.
+pub fn truncate<F>(pay: &Pay, limit: TruncationLimit, encode: F) -> Result<(Pay, TruncationReport), BysqrError>
+where
+    F: Fn(&Pay) -> Result<String, BysqrError>,
+{
+    let mut truncated = pay.clone();
+    let mut report = TruncationReport::default();
+
+    truncate_payment_notes(&mut truncated, &mut report);
+
+    truncate_fields(truncated, truncatable_fields(pay), limit, encode, report)
+}
+
+/// Shorten text fields of the Invoice in ascending priority, until the encoded content fits into the limit.
+/// Required fields are cut to one character at most. Fails when the content does not fit even then.
+pub fn truncate_invoice<F>(invoice: &Invoice, limit: TruncationLimit, encode: F) -> Result<(Invoice, TruncationReport), BysqrError>
+where
+    F: Fn(&Invoice) -> Result<String, BysqrError>,
+{
+    let fields = text_fields(invoice, invoice_fields, |path| {
+        INVOICE_FIELDS.iter().find(|(name, _, _)| *name == path).map(|(_, priority, required)| (*priority, *required))
+    });
+
+    truncate_fields(invoice.clone(), fields, limit, encode, TruncationReport::default())
+}
+
+/// Shorten text fields of the InvoiceItems in ascending priority, until the encoded content fits into the limit.
+/// Required fields are cut to one character at most. Fails when the content does not fit even then.
+pub fn truncate_invoice_items<F>(items: &InvoiceItems, limit: TruncationLimit, encode: F) -> Result<(InvoiceItems, TruncationReport), BysqrError>
+where
+    F: Fn(&InvoiceItems) -> Result<String, BysqrError>,
+{
+    let fields = text_fields(items, invoice_items_fields, |path| {
+        // Fields of lines are listed without the path of the line, e.g. "InvoiceLines.InvoiceLine[0]."
+        let name = match path.split_once("].") {
+            Some((_, name)) => name,
+            None => path,
+        };
+
+        INVOICE_ITEMS_FIELDS.iter().find(|(field, _, _)| *field == name).map(|(_, priority, required)| (*priority, *required))
+    });
+
+    truncate_fields(items.clone(), fields, limit, encode, TruncationReport::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{truncate, truncate_invoice, TruncationLimit, PAYMENT_NOTE_MAX_LENGTH};
+    use crate::encoder::{encode, encode_invoice};
+    use crate::error::BysqrError;
+    use crate::models::{try_deserialize_invoice, try_deserialize_pay, DirectDebitExt, Pay, PaymentOptions};
+
+    /// Text which does not compress well, so that each removed character shortens the content.
+    fn text(length: usize) -> String {
+        let mut seed: u32 = 7;
+
+        (0..length)
+            .map(|_| {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                (b'a' + (seed >> 16) as u8 % 26) as char
+            })
+            .collect()
+    }
+
+    fn pay() -> Pay {
+        let mut pay = try_deserialize_pay(include_str!("../example/payment.xml")).unwrap();
+        pay.invoice_id = Some(text(10));
+        pay.payments.payment[0].payment_note = Some(text(120));
+        pay
+    }
+
+    #[test]
+    fn keeps_content_within_limit() {
+        let pay = pay();
+        let length = encode(&pay).unwrap().len();
+
+        let (truncated, report) = truncate(&pay, TruncationLimit::Length(length), encode).unwrap();
+
+        assert_eq!(truncated, pay);
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn cuts_lowest_priority_field_only_as_needed() {
+        let pay = pay();
+        let length = encode(&pay).unwrap().len();
+
+        let (truncated, report) = truncate(&pay, TruncationLimit::Length(length - 16), encode).unwrap();
+        let note = truncated.payments.payment[0].payment_note.clone().unwrap();
+
+        assert!(encode(&truncated).unwrap().len() <= length - 16);
+        assert!(!note.is_empty() && note.len() < 120);
+        assert_eq!(truncated.invoice_id, pay.invoice_id);
+        assert_eq!(report.fields.len(), 1);
+        assert_eq!(report.fields[0].path, "Payments.Payment[0].PaymentNote");
+    }
+
+    #[test]
+    fn cuts_fields_in_order_of_priority() {
+        let pay = pay();
+        let mut without_note = pay.clone();
+        without_note.payments.payment[0].payment_note = None;
+        let length = encode(&without_note).unwrap().len();
+
+        let (truncated, report) = truncate(&pay, TruncationLimit::Length(length - 8), encode).unwrap();
+        let paths: Vec<&str> = report.fields.iter().map(|field| field.path.as_str()).collect();
+
+        assert_eq!(truncated.payments.payment[0].payment_note, None);
+        assert_eq!(paths[..2], ["Payments.Payment[0].PaymentNote", "InvoiceID"]);
+        assert_eq!(report.fields[0].truncated, "");
+        assert!(report.fields.windows(2).all(|fields| fields[0].priority <= fields[1].priority));
+    }
+
+    #[test]
+    fn cuts_long_payment_note_first() {
+        let mut pay = pay();
+        pay.payments.payment[0].payment_note = Some(text(200));
+
+        let (truncated, report) = truncate(&pay, TruncationLimit::Length(1000), encode).unwrap();
+
+        assert_eq!(truncated.payments.payment[0].payment_note, Some(text(PAYMENT_NOTE_MAX_LENGTH)));
+        assert_eq!(report.fields.len(), 1);
+        assert_eq!(report.fields[0].original, text(200));
+    }
+
+    #[test]
+    fn fails_when_content_does_not_fit() {
+        assert!(matches!(truncate(&pay(), TruncationLimit::Length(10), encode), Err(BysqrError::TruncationLimit)));
+    }
+
+    #[test]
+    fn cuts_invoice_fields_in_order_of_priority_and_keeps_required_fields() {
+        let mut invoice = try_deserialize_invoice(include_str!("../example/invoice.xml")).unwrap();
+        invoice.order_id = Some(text(10));
+        invoice.single_invoice_line.as_mut().unwrap().item_name = Some(text(30));
+
+        let mut shortest = invoice.clone();
+        shortest.single_invoice_line.as_mut().unwrap().item_name = Some(text(1));
+        let length = encode_invoice(&shortest).unwrap().len();
+
+        let (truncated, report) = truncate_invoice(&invoice, TruncationLimit::Length(length - 8), encode_invoice).unwrap();
+        let paths: Vec<&str> = report.fields.iter().map(|field| field.path.as_str()).collect();
+
+        assert_eq!(paths[..2], ["SingleInvoiceLine.ItemName", "OrderID"]);
+        assert_eq!(truncated.single_invoice_line.unwrap().item_name, Some(text(1)));
+        assert!(!truncated.invoice_id.is_empty());
+    }
+
+    #[test]
+    fn cuts_direct_debit_fields_in_order_of_priority() {
+        let ids = text(105);
+        let mut pay = pay();
+        pay.invoice_id = None;
+        let payment = &mut pay.payments.payment[0];
+        payment.payment_note = None;
+        payment.payment_options = PaymentOptions::DIRECT_DEBIT;
+        payment.direct_debit_ext = Some(DirectDebitExt {
+            contract_id: Some(ids[..35].to_string()),
+            creditor_id: Some(ids[35..70].to_string()),
+            mandate_id: Some(ids[70..].to_string()),
+            ..DirectDebitExt::default()
+        });
+
+        let mut shortest = pay.clone();
+        shortest.payments.payment[0].variable_symbol = None;
+        shortest.payments.payment[0].direct_debit_ext.as_mut().unwrap().contract_id = None;
+        let length = encode(&shortest).unwrap().len();
+
+        let (truncated, report) = truncate(&pay, TruncationLimit::Length(length - 8), encode).unwrap();
+        let paths: Vec<&str> = report.fields.iter().map(|field| field.path.as_str()).collect();
+        let ext = truncated.payments.payment[0].direct_debit_ext.clone().unwrap();
+
+        assert_eq!(paths, [
+            "Payments.Payment[0].VariableSymbol",
+            "Payments.Payment[0].DirectDebitExt.ContractID",
+            "Payments.Payment[0].DirectDebitExt.CreditorID",
+        ]);
+        assert!(ext.creditor_id.unwrap().len() < 35);
+        assert_eq!(ext.mandate_id, Some(ids[70..].to_string()));
+    }
+}
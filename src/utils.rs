@@ -1,12 +1,15 @@
 use std::fs;
-use std::path::PathBuf;
+use std::io;
+use std::path::Path;
 
-pub fn ensure_directory_for_file(path: &PathBuf) {
+pub fn ensure_directory_for_file(path: &Path) -> io::Result<()> {
     if path.is_dir() {
-        panic!("the path is not a file path");
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "the path is not a file path"));
     }
 
     if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent).unwrap();
+        fs::create_dir_all(parent)?;
     }
+
+    Ok(())
 }
@@ -0,0 +1,252 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::control::{pay_fields, sanitize_pay, ControlCharacters};
+use crate::encoder::{encode_with_options, pay_to_seq, EncodeOptions};
+use crate::error::{BysqrError, Rule};
+use crate::field::is_within;
+use crate::models::{try_deserialize_pay_lenient, Pay};
+use crate::qr;
+use crate::truncation::{truncate_payment_notes, TruncationReport};
+
+/// Non-fatal finding, the document can still be encoded.
+#[derive(Debug, Clone)]
+pub struct ValidationWarning {
+    /// Path to the field, for example "Payments.Payment[0].PaymentNote".
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Warning: The {} {}", self.path, self.message)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    /// Fields which do not satisfy rules of the specification, the document can not be encoded.
+    pub errors: Vec<BysqrError>,
+    pub warnings: Vec<ValidationWarning>,
+}
+
+impl ValidationReport {
+    /// True when the document can be encoded. Warnings do not affect validity.
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    fn warn(&mut self, path: String, message: &str) {
+        self.warnings.push(ValidationWarning { path, message: message.to_string() });
+    }
+
+    /// Serialize to JSON with "valid" flag, "errors" and "warnings". Each of them has "path" of the field,
+    /// null for errors not related to a single field, the "value" of invalid field and the "message".
+    pub fn to_json(&self) -> Result<String, BysqrError> {
+        let errors = self.errors.iter().map(ReportEntry::from_error);
+
+        let warnings = self.warnings.iter().map(|warning| ReportEntry {
+            path: Some(&warning.path),
+            value: None,
+            message: warning.message.clone(),
+        });
+
+        let report = JsonReport { valid: self.is_valid(), errors: errors.collect(), warnings: warnings.collect() };
+
+        serde_json::to_string_pretty(&report).map_err(|e| BysqrError::Serialize(e.to_string()))
+    }
+}
+
+/// Error or warning of the JSON report.
+#[derive(Serialize)]
+pub(crate) struct ReportEntry<'a> {
+    path: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<&'a str>,
+    message: String,
+}
+
+impl<'a> ReportEntry<'a> {
+    pub(crate) fn from_error(error: &'a BysqrError) -> Self {
+        match error {
+            BysqrError::InvalidField { path, rule: Rule::Required, .. } => {
+                ReportEntry { path: Some(path), value: None, message: Rule::Required.to_string() }
+            }
+            BysqrError::InvalidField { path, value, rule } => {
+                ReportEntry { path: Some(path), value: Some(value), message: rule.to_string() }
+            }
+            error => ReportEntry { path: None, value: None, message: error.to_string() },
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    valid: bool,
+    errors: Vec<ReportEntry<'a>>,
+    warnings: Vec<ReportEntry<'a>>,
+}
+
+/// Check every field of the Pay against the rules used by the encoder. Unlike encode,
+/// which stops at the first invalid field, all errors are reported together with warnings.
+pub fn validate(pay: &Pay) -> ValidationReport {
+    validate_with_options(pay, &EncodeOptions::default())
+}
+
+/// Check the Pay the same way as validate, against the rules used by encode_with_options with the options.
+/// Payment notes over the maximum length are reported as warnings when the truncation is requested.
+pub fn validate_with_options(pay: &Pay, options: &EncodeOptions) -> ValidationReport {
+    let mut report = ValidationReport::default();
+
+    // Fields are checked the same way encode does, after control characters are replaced
+    let mut sanitized = pay.clone();
+    let mut fields: Vec<String> = Vec::new();
+    pay_fields(&mut sanitized, &mut |path, value| {
+        if value.chars().any(char::is_control) {
+            fields.push(path);
+        }
+    });
+
+    if options.control_characters == ControlCharacters::Replace {
+        for path in fields {
+            report.warn(path, "contains control characters, they are replaced by spaces");
+        }
+    }
+
+    if options.truncate.is_some() {
+        let mut truncated = TruncationReport::default();
+        truncate_payment_notes(&mut sanitized, &mut truncated);
+
+        for field in truncated.fields {
+            report.warn(field.path, "is longer than 140 characters, it is truncated");
+        }
+    }
+
+    sanitize_pay(&mut sanitized, options.control_characters, &mut report.errors);
+    pay_to_seq(&sanitized, &mut report.errors);
+
+    // Valid fields may still produce content too large for the payload or for the QR code
+    if report.errors.is_empty() {
+        if let Err(error) = encode_with_options(&sanitized, options).and_then(|(encoded, _)| qr::create_code(&encoded)) {
+            report.errors.push(error);
+        }
+    }
+
+    for (index, payment) in pay.payments.payment.iter().enumerate() {
+        let path = format!("Payments.Payment[{}]", index);
+
+        for (account_index, bank_account) in payment.bank_accounts.bank_account.iter().enumerate() {
+            if bank_account.bic.is_none() {
+                report.warn(format!("{}.BankAccounts.BankAccount[{}].BIC", path, account_index), "is missing, some banks require it");
+            }
+        }
+    }
+
+    report
+}
+
+/// Deserialize the Pay XML or JSON document and validate it. Values which do not satisfy their type, such as
+/// IBAN, symbols or dates, are reported with path of their field together with errors and warnings of validate.
+pub fn validate_source(content: &str, strict: bool) -> ValidationReport {
+    let (pay, mut errors) = match try_deserialize_pay_lenient(content, strict) {
+        Ok(deserialized) => deserialized,
+        Err(error) => return ValidationReport { errors: vec![error], warnings: Vec::new() },
+    };
+
+    let mut report = validate(&pay);
+
+    // Invalid values are replaced by placeholders in the Pay, so the fields containing them are not reported again
+    let is_reported = |path: &str| {
+        errors.iter().any(|error| matches!(error, BysqrError::InvalidField { path: invalid, .. } if is_within(invalid, path)))
+    };
+
+    report.errors.retain(|error| !matches!(error, BysqrError::InvalidField { path, .. } if is_reported(path)));
+    report.warnings.retain(|warning| !is_reported(&warning.path));
+
+    errors.append(&mut report.errors);
+    report.errors = errors;
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::validate_source;
+    use crate::error::{BysqrError, Rule};
+
+    fn payment(accounts: &str, fields: &str) -> String {
+        format!(
+            "<Payment><PaymentOptions>paymentorder</PaymentOptions><Amount>10</Amount><CurrencyCode>EUR</CurrencyCode>\
+             {}<BankAccounts>{}</BankAccounts><BeneficiaryName>Test</BeneficiaryName></Payment>",
+            fields, accounts,
+        )
+    }
+
+    #[test]
+    fn reports_every_error_with_path_and_missing_bic_as_warning() {
+        let first = payment(
+            "<BankAccount><IBAN>SK8811000000002945102347</IBAN></BankAccount>\
+             <BankAccount><IBAN>SK0011000000002945102347</IBAN><BIC>TATRSKBX</BIC></BankAccount>",
+            &format!("<BeneficiaryAddressLine1>{}</BeneficiaryAddressLine1>", "x".repeat(71)),
+        );
+        let second = payment(
+            "<BankAccount><IBAN>SK8811000000002945102347</IBAN><BIC>TATR</BIC></BankAccount>\
+             <BankAccount><IBAN>SK88 1100 0000 0029 4510 2347</IBAN><BIC>TATRSKBX</BIC></BankAccount>",
+            "<VariableSymbol>12A</VariableSymbol>",
+        );
+        let content = format!("<?xml version=\"1.0\"?><Pay type=\"Pay\"><Payments>{}{}</Payments></Pay>", first, second);
+
+        let report = validate_source(&content, false);
+        let errors: Vec<(&str, &Rule)> = report
+            .errors
+            .iter()
+            .map(|error| match error {
+                BysqrError::InvalidField { path, rule, .. } => (path.as_str(), rule),
+                other => panic!("unexpected error: {}", other),
+            })
+            .collect();
+        let warnings: Vec<&str> = report.warnings.iter().map(|warning| warning.path.as_str()).collect();
+
+        assert_eq!(errors, [
+            ("Payments.Payment[0].BankAccounts.BankAccount[1].IBAN", &Rule::Checksum),
+            ("Payments.Payment[1].VariableSymbol", &Rule::Pattern(r"^\d{0,10}$")),
+            ("Payments.Payment[1].BankAccounts.BankAccount[0].BIC", &Rule::Pattern(r"^[A-Z]{4}[A-Z]{2}[A-Z\d]{2}([A-Z\d]{3})?$")),
+            ("Payments.Payment[0].BeneficiaryAddressLine1", &Rule::Pattern(r"^.{0,70}$")),
+        ]);
+        assert_eq!(warnings, ["Payments.Payment[0].BankAccounts.BankAccount[0].BIC"]);
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn serializes_report_to_json() {
+        let content = format!(
+            "<?xml version=\"1.0\"?><Pay type=\"Pay\"><Payments>{}</Payments></Pay>",
+            payment("<BankAccount><IBAN>SK0011000000002945102347</IBAN></BankAccount>", ""),
+        );
+
+        let report: serde_json::Value = serde_json::from_str(&validate_source(&content, false).to_json().unwrap()).unwrap();
+
+        assert_eq!(report, json!({
+            "valid": false,
+            "errors": [{
+                "path": "Payments.Payment[0].BankAccounts.BankAccount[0].IBAN",
+                "value": "SK0011000000002945102347",
+                "message": "must have valid check digits",
+            }],
+            "warnings": [{
+                "path": "Payments.Payment[0].BankAccounts.BankAccount[0].BIC",
+                "message": "is missing, some banks require it",
+            }],
+        }));
+
+        let report: serde_json::Value = serde_json::from_str(&validate_source("{}", false).to_json().unwrap()).unwrap();
+
+        assert_eq!(report["valid"], json!(false));
+        assert_eq!(report["errors"][0]["path"], json!(null));
+        assert!(report["errors"][0].get("value").is_none());
+        assert_eq!(report["warnings"], json!([]));
+    }
+}
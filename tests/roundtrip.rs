@@ -0,0 +1,50 @@
+use std::fs;
+use std::path::Path;
+
+use bysqr::decoder::{decode, decode_invoice, decode_invoice_items};
+use bysqr::encoder::{encode, encode_invoice, encode_invoice_items};
+use bysqr::models::{try_deserialize_invoice, try_deserialize_invoice_items, try_deserialize_pay};
+
+/// Encode each example of the document type and check it decodes to the same document.
+fn assert_examples_roundtrip(prefix: &str, roundtrip: impl Fn(&str)) {
+    let mut count = 0;
+
+    for entry in fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("example")).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_stem().unwrap().to_string_lossy().to_string();
+
+        if name == prefix {
+            roundtrip(&fs::read_to_string(&path).unwrap());
+            count += 1;
+        }
+    }
+
+    assert!(count > 0, "no examples of {}", prefix);
+}
+
+#[test]
+fn payment_examples_roundtrip() {
+    assert_examples_roundtrip("payment", |source| {
+        let pay = try_deserialize_pay(source).unwrap();
+
+        assert_eq!(decode(&encode(&pay).unwrap()).unwrap(), pay);
+    });
+}
+
+#[test]
+fn invoice_examples_roundtrip() {
+    assert_examples_roundtrip("invoice", |source| {
+        let invoice = try_deserialize_invoice(source).unwrap();
+
+        assert_eq!(decode_invoice(&encode_invoice(&invoice).unwrap()).unwrap(), invoice);
+    });
+}
+
+#[test]
+fn invoice_items_examples_roundtrip() {
+    assert_examples_roundtrip("invoice_items", |source| {
+        let items = try_deserialize_invoice_items(source).unwrap();
+
+        assert_eq!(decode_invoice_items(&encode_invoice_items(&items).unwrap()).unwrap(), items);
+    });
+}